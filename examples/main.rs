@@ -1,22 +1,20 @@
-use lil_http::{Body, Response, Server};
+use lil_http::{Json, Request, Response, Server};
+use serde_json::Value;
 
 #[tokio::main]
 async fn main() {
     let mut http = Server::new().await.unwrap();
 
     http.routes
-        .get("/", |request| {
+        .get("/", |request: Request| {
             println!("Received {} request to {}", request.method, request.path);
 
-            Response::text(
-                format!(
-                    "Hello, {}!",
-                    request.query.get("name").unwrap_or(&"World".to_string())
-                )
-                .as_str(),
+            format!(
+                "Hello, {}!",
+                request.query.get("name").unwrap_or(&"World".to_string())
             )
         })
-        .get("/api/user", |request| {
+        .get("/api/user", |request: Request| {
             println!("Received {} request to {}", request.method, request.path);
 
             Response::json(&serde_json::json!({
@@ -24,13 +22,12 @@ async fn main() {
                 "age": 20,
             }))
         })
-        .post("/api/hello", |request| {
-            println!("Received {} request to {}", request.method, request.path);
-
-            let Body::Json(body) = request.body else {
-                return Response::invalid_request();
-            };
+        .get("/api/slow", || async {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 
+            "Sorry for the wait!"
+        })
+        .post("/api/hello", |Json(body): Json<Value>| {
             let Some(name) = body.get("name") else {
                 return Response::invalid_request();
             };
@@ -40,5 +37,5 @@ async fn main() {
             }))
         });
 
-    http.run().await;
+    http.run_until_signal().await;
 }
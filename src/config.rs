@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// Limits applied to incoming connections and requests, and other server settings.
+///
+/// Requests exceeding these limits are rejected before reaching the router,
+/// with a 431 Request Header Fields Too Large or 413 Content Too Large response.
+/// Persistent connections are closed once they've been idle for too long, or have served too many requests.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::Server;
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.config.max_body_size = 10 * 1024 * 1024;
+/// http.config.server_header = Some("lil-http".to_string());
+/// # }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Config {
+    /// The maximum size of the request line and headers, in bytes.
+    pub max_head_size: usize,
+    /// The maximum number of headers a request can have.
+    pub max_headers: usize,
+    /// The maximum size of a request body, in bytes.
+    pub max_body_size: usize,
+    /// How long to wait for the client to send more data before closing the connection.
+    pub idle_timeout: Duration,
+    /// The maximum number of requests to handle on a single connection before closing it.
+    pub max_requests_per_connection: usize,
+    /// How long to wait for in-flight requests to finish when shutting down, before dropping their connections.
+    pub shutdown_timeout: Duration,
+    /// The value of the `Server` header added to every response, if any.
+    pub server_header: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_head_size: 16 * 1024,
+            max_headers: 100,
+            max_body_size: 2 * 1024 * 1024,
+            idle_timeout: Duration::from_secs(30),
+            max_requests_per_connection: 1000,
+            shutdown_timeout: Duration::from_secs(30),
+            server_header: None,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use serde_json::json;
+use std::{
+    fmt::{Debug, Display, Formatter},
+    sync::Arc,
+};
+
+use crate::{
+    http::{IntoResponse, Response, StatusCode},
+    Body,
+};
+
+/// An error returned by a handler, along with the status code it should be reported with.
+///
+/// Any [`std::error::Error`] can be converted into this type (with a 500 Internal Server Error
+/// status), so handlers returning `Result<T, Error>` can use the `?` operator. By default,
+/// errors are turned into an [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) `application/problem+json`
+/// response, which can be customized with [`Server::error_handler`](crate::Server::error_handler).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Error, Path, Server, StatusCode};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.get("/users/:id", |Path(id): Path<String>| -> Result<String, Error> {
+///     let id: u32 = id
+///         .parse()
+///         .map_err(|e| Error::from(e).with_status(StatusCode::BadRequest))?;
+///
+///     Ok(format!("User #{id}"))
+/// });
+/// # }
+/// ```
+pub struct Error {
+    /// The status code the error should be reported with.
+    status: StatusCode,
+    /// The underlying error.
+    inner: anyhow::Error,
+}
+
+impl Error {
+    /// Create an error with the given status code and message.
+    #[must_use]
+    pub fn new<M>(status: StatusCode, message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Self {
+            status,
+            inner: anyhow::Error::msg(message),
+        }
+    }
+
+    /// Set the status code the error should be reported with.
+    #[must_use]
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+
+        self
+    }
+
+    /// The status code the error should be reported with.
+    #[must_use]
+    pub const fn status(&self) -> &StatusCode {
+        &self.status
+    }
+
+    /// Build the default `application/problem+json` response for this error.
+    /// The error message is only included for client errors, to avoid leaking internal details.
+    pub(crate) fn problem(&self) -> Response {
+        let mut problem = json!({
+            "type": "about:blank",
+            "title": self.status.reason_phrase(),
+            "status": self.status.as_u16(),
+        });
+
+        if !self.status.is_server_error() {
+            problem["detail"] = json!(self.inner.to_string());
+        }
+
+        Response::ok()
+            .status(self.status.clone())
+            .header("Content-Type", "application/problem+json")
+            .body(Body::Json(problem))
+    }
+}
+
+impl<E> From<E> for Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(error: E) -> Self {
+        Self {
+            status: StatusCode::InternalServerError,
+            inner: error.into(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Error")
+            .field("status", &self.status)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let mut response = self.problem();
+        response.error = Some(Arc::new(self));
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_into_problem_response() {
+        let response = Error::new(StatusCode::NotFound, "User not found").into_response();
+
+        assert_eq!(response.status_code, StatusCode::NotFound);
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"application/problem+json".to_string())
+        );
+        assert_eq!(
+            response.body,
+            Body::Json(json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "User not found",
+            }))
+        );
+        assert_eq!(response.error().unwrap().to_string(), "User not found");
+    }
+
+    #[test]
+    fn test_error_from_std_error_hides_details() {
+        let error = Error::from("nope".parse::<u32>().unwrap_err());
+
+        assert_eq!(error.status(), &StatusCode::InternalServerError);
+        assert_eq!(
+            error.into_response().body,
+            Body::Json(json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+            }))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use serde::de::{
+    self,
+    value::{BorrowedStrDeserializer, Error, MapDeserializer},
+    DeserializeOwned, Deserializer, IntoDeserializer, Visitor,
+};
+use std::collections::HashMap;
+
+/// Deserialize a value from a map of strings, like the query, headers or path parameters of a request.
+///
+/// Values are parsed into whichever type the target expects, so `?page=2` can be read into a `u32`.
+///
+/// # Errors
+///
+/// Will return an error if the map doesn't match the shape of `T`, or if a value can't be parsed.
+pub fn from_map<T: DeserializeOwned>(map: &HashMap<String, String>) -> Result<T, Error> {
+    T::deserialize(MapDeserializer::new(
+        map.iter()
+            .map(|(key, value)| (key.as_str(), Value(value.as_str()))),
+    ))
+}
+
+/// Deserialize a value from a single string, parsing it into whichever type the target expects.
+///
+/// # Errors
+///
+/// Will return an error if the value can't be parsed into `T`.
+pub fn from_str<T: DeserializeOwned>(value: &str) -> Result<T, Error> {
+    T::deserialize(Value(value))
+}
+
+/// A string value that can be deserialized into any scalar type.
+struct Value<'de>(&'de str);
+
+impl<'de> IntoDeserializer<'de, Error> for Value<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.0.parse().map_err(|_| {
+                    de::Error::custom(format!("invalid value `{}`", self.0))
+                })?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Value<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        BorrowedStrDeserializer::new(self.0).deserialize_enum(name, variants, visitor)
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_map_parses_values() {
+        let map = HashMap::from([
+            ("page".to_string(), "2".to_string()),
+            ("name".to_string(), "Miguel".to_string()),
+        ]);
+
+        let values: HashMap<String, String> = from_map(&map).unwrap();
+        assert_eq!(values, map);
+
+        let values: HashMap<String, u32> =
+            from_map(&HashMap::from([("page".to_string(), "2".to_string())])).unwrap();
+        assert_eq!(values.get("page"), Some(&2));
+
+        assert!(from_map::<HashMap<String, u32>>(&map).is_err());
+    }
+
+    #[test]
+    fn test_from_str_parses_scalars() {
+        assert_eq!(from_str::<u32>("42").unwrap(), 42);
+        assert_eq!(from_str::<String>("42").unwrap(), "42");
+        assert_eq!(from_str::<Option<bool>>("true").unwrap(), Some(true));
+        assert!(from_str::<u32>("forty-two").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use serde::de::DeserializeOwned;
+use std::any::type_name;
+
+use crate::{
+    error::Error,
+    http::{Body, IntoResponse, Request, Response, StatusCode},
+    state::State,
+};
+
+mod de;
+
+/// Types that can be extracted from a request, to be used as handler arguments.
+///
+/// Handlers can take any number of extractors as arguments, and they will be
+/// extracted in order before the handler runs. If an extractor fails, its error
+/// response (usually a 400 Bad Request) is returned instead of calling the handler.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Json, Path, Query, Response, Server};
+/// use serde_json::{json, Value};
+/// use std::collections::HashMap;
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.post(
+///     "/users/:id",
+///     |Path(id): Path<u32>, Query(query): Query<HashMap<String, String>>, Json(body): Json<Value>| {
+///         Response::json(&json!({ "id": id, "query": query, "body": body }))
+///     },
+/// );
+/// # }
+/// ```
+pub trait FromRequest: Sized {
+    /// Extract the value from the request.
+    ///
+    /// # Errors
+    ///
+    /// Will return the response to send back if the value can't be extracted.
+    #[allow(clippy::result_large_err)] // Rejections are rare, and returned straight to the client.
+    fn from_request(request: &Request) -> Result<Self, Response>;
+}
+
+/// Build the response returned when an extractor fails.
+fn rejection(message: &str) -> Response {
+    Response::invalid_request().body(Body::Text(message.to_string()))
+}
+
+impl FromRequest for Request {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        Ok(request.clone())
+    }
+}
+
+impl FromRequest for Body {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        Ok(request.body.clone())
+    }
+}
+
+/// Optional extractors never fail: they resolve to `None` instead.
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        Ok(T::from_request(request).ok())
+    }
+}
+
+/// Extracting state fails with a 500 Internal Server Error if no state of type `T` has been registered on the server.
+impl<T: Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        request.state().ok_or_else(|| {
+            Error::new(
+                StatusCode::InternalServerError,
+                format!("state of type `{}` not registered", type_name::<T>()),
+            )
+            .into_response()
+        })
+    }
+}
+
+/// Extract and deserialize an `application/json` request body.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        let Body::Json(body) = &request.body else {
+            return Err(rejection("Expected an application/json body"));
+        };
+
+        serde_json::from_value(body.clone())
+            .map(Self)
+            .map_err(|e| rejection(&format!("Invalid JSON body: {e}")))
+    }
+}
+
+/// Extract and deserialize the query string of a request.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        de::from_map(&request.query)
+            .map(Self)
+            .map_err(|e| rejection(&format!("Invalid query: {e}")))
+    }
+}
+
+/// Extract and deserialize the path parameters captured by the matched route.
+///
+/// Parameters can be deserialized into a map or a struct with a field per parameter.
+/// If the route has a single parameter, it can also be deserialized directly (e.g. `Path<u32>`).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Path<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Path<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        de::from_map(&request.params)
+            .or_else(|e| match request.params.values().next() {
+                Some(value) if request.params.len() == 1 => de::from_str(value),
+                _ => Err(e),
+            })
+            .map(Self)
+            .map_err(|e| rejection(&format!("Invalid path parameters: {e}")))
+    }
+}
+
+/// Extract and deserialize the headers of a request.
+///
+/// Header names are lowercase, so struct fields for headers like `Content-Type`
+/// should be renamed accordingly (e.g. `#[serde(rename = "content-type")]`).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Header<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Header<T> {
+    fn from_request(request: &Request) -> Result<Self, Response> {
+        de::from_map(&request.headers)
+            .map(Self)
+            .map_err(|e| rejection(&format!("Invalid headers: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http::{Method, Version},
+        state::StateMap,
+    };
+    use serde_json::{json, Value};
+    use std::{collections::HashMap, sync::Arc};
+
+    fn request() -> Request {
+        Request {
+            method: Method::Post,
+            path: "/users/42".to_string(),
+            version: Version::Http11,
+            query: HashMap::from([("page".to_string(), "2".to_string())]),
+            params: HashMap::from([("id".to_string(), "42".to_string())]),
+            headers: HashMap::from([("x-api-key".to_string(), "secret".to_string())]),
+            body: Body::Json(json!({ "name": "Miguel" })),
+            trailers: HashMap::new(),
+            state: StateMap::default(),
+            connection: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_extract_json() {
+        let Json(body) = Json::<Value>::from_request(&request()).unwrap();
+        assert_eq!(body, json!({ "name": "Miguel" }));
+
+        let request = Request {
+            body: Body::Text("Miguel".to_string()),
+            ..request()
+        };
+        let response = Json::<Value>::from_request(&request).unwrap_err();
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn test_extract_query() {
+        let Query(query) = Query::<HashMap<String, u32>>::from_request(&request()).unwrap();
+        assert_eq!(query.get("page"), Some(&2));
+
+        let response = Query::<HashMap<String, bool>>::from_request(&request()).unwrap_err();
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn test_extract_path() {
+        let Path(id) = Path::<u32>::from_request(&request()).unwrap();
+        assert_eq!(id, 42);
+
+        let Path(params) = Path::<HashMap<String, String>>::from_request(&request()).unwrap();
+        assert_eq!(params.get("id"), Some(&"42".to_string()));
+
+        assert!(Path::<bool>::from_request(&request()).is_err());
+    }
+
+    #[test]
+    fn test_extract_headers() {
+        let Header(headers) = Header::<HashMap<String, String>>::from_request(&request()).unwrap();
+        assert_eq!(headers.get("x-api-key"), Some(&"secret".to_string()));
+    }
+
+    #[test]
+    fn test_extract_optional() {
+        let request = Request {
+            body: Body::None,
+            ..request()
+        };
+
+        assert_eq!(Option::<Json<Value>>::from_request(&request).unwrap(), None);
+    }
+
+    #[test]
+    fn test_extract_missing_state() {
+        let response = State::<String>::from_request(&request()).unwrap_err();
+        assert_eq!(response.status_code, StatusCode::InternalServerError);
+        assert!(response.error.is_some());
+    }
+}
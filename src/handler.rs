@@ -0,0 +1,131 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use crate::{
+    extract::FromRequest,
+    http::{IntoResponse, Request, Response},
+};
+
+/// A pinned, boxed future that can be sent across threads.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// A type-erased route handler.
+pub type BoxedHandler = Arc<dyn Fn(Request) -> BoxFuture<Response> + Send + Sync>;
+
+/// Marks a [`HandlerOutput`] that is immediately available.
+pub enum Immediate {}
+
+/// Marks a [`HandlerOutput`] that is only available once a future resolves.
+pub enum Deferred {}
+
+/// The value returned by a route handler.
+///
+/// Handlers can either return anything implementing [`IntoResponse`] directly, or a future that
+/// resolves to one (for example, by being an `async` function or returning an `async` block).
+/// The `M` parameter only exists to tell both implementations apart.
+pub trait HandlerOutput<M>: Send + 'static {
+    /// Turn the value into a future that resolves to the response.
+    fn into_future(self) -> BoxFuture<Response>;
+}
+
+impl<T> HandlerOutput<Immediate> for T
+where
+    T: IntoResponse + Send + 'static,
+{
+    fn into_future(self) -> BoxFuture<Response> {
+        Box::pin(std::future::ready(self.into_response()))
+    }
+}
+
+impl<F> HandlerOutput<Deferred> for F
+where
+    F: Future + Send + 'static,
+    F::Output: IntoResponse,
+{
+    fn into_future(self) -> BoxFuture<Response> {
+        Box::pin(async move { self.await.into_response() })
+    }
+}
+
+/// A function that can handle requests.
+///
+/// Handlers are functions or closures taking any number of [extractors](FromRequest) as arguments
+/// (up to 8), and returning a [`HandlerOutput`]. The `Args` parameter holds the output marker and
+/// the argument types, and only exists to tell the implementations apart.
+///
+/// Since the argument types can't be inferred, closure arguments need a type annotation
+/// (e.g. `|request: Request| ...`).
+pub trait Handler<Args>: Send + Sync + 'static {
+    /// Extract the arguments from the request, and call the handler with them.
+    fn call(&self, request: Request) -> BoxFuture<Response>;
+}
+
+macro_rules! impl_handler {
+    ($($ty:ident $arg:ident),*) => {
+        impl<F, R, M, $($ty,)*> Handler<(M, $($ty,)*)> for F
+        where
+            F: Fn($($ty),*) -> R + Send + Sync + 'static,
+            R: HandlerOutput<M>,
+            $($ty: FromRequest,)*
+        {
+            #[allow(unused_variables)]
+            fn call(&self, request: Request) -> BoxFuture<Response> {
+                $(
+                    let $arg = match $ty::from_request(&request) {
+                        Ok(value) => value,
+                        Err(response) => return Box::pin(std::future::ready(response)),
+                    };
+                )*
+
+                self($($arg),*).into_future()
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(T1 t1);
+impl_handler!(T1 t1, T2 t2);
+impl_handler!(T1 t1, T2 t2, T3 t3);
+impl_handler!(T1 t1, T2 t2, T3 t3, T4 t4);
+impl_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5);
+impl_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6);
+impl_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7);
+impl_handler!(T1 t1, T2 t2, T3 t3, T4 t4, T5 t5, T6 t6, T7 t7, T8 t8);
+
+/// Erase the type of a handler, so it can be stored alongside others.
+pub fn boxed<Args>(handler: impl Handler<Args>) -> BoxedHandler {
+    Arc::new(move |request| handler.call(request))
+}
+
+/// A future that catches panics while polling the inner future.
+pub struct CatchUnwind<F>(pub F);
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.0;
+
+        match catch_unwind(AssertUnwindSafe(|| Pin::new(inner).poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+/// Get the message a panic was raised with, if any.
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
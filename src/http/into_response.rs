@@ -0,0 +1,197 @@
+use serde_json::Value;
+
+use super::{Body, Response, StatusCode};
+
+/// Types that can be turned into a [`Response`], and can therefore be returned from handlers.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Path, Request, Response, Server, StatusCode};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes
+///     .get("/", || "Hello, World!")
+///     .post("/users", || (StatusCode::Ok, [("X-Created", "true")], "Created!"))
+///     .get("/users/:id", |Path(id): Path<u32>| (id == 1).then_some("Miguel"));
+/// # }
+/// ```
+pub trait IntoResponse {
+    /// Turn the value into a response.
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+/// An empty 200 OK response.
+impl IntoResponse for () {
+    fn into_response(self) -> Response {
+        Response::ok()
+    }
+}
+
+/// An empty response with the given status code.
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response {
+        Response::ok().status(self)
+    }
+}
+
+/// A `text/plain` response.
+impl IntoResponse for &str {
+    fn into_response(self) -> Response {
+        Response::text(self)
+    }
+}
+
+/// A `text/plain` response.
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        Response::text(&self)
+    }
+}
+
+/// An `application/octet-stream` response.
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> Response {
+        Response::ok()
+            .header("Content-Type", "application/octet-stream")
+            .body(Body::Bytes(self))
+    }
+}
+
+/// An `application/json` response.
+impl IntoResponse for Value {
+    fn into_response(self) -> Response {
+        Response::json(&self)
+    }
+}
+
+/// Override the status code of a response.
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> Response {
+        let (status, response) = self;
+
+        response.into_response().status(status)
+    }
+}
+
+/// Override the status code of a response, and add some headers to it.
+impl<T, H, K, V> IntoResponse for (StatusCode, H, T)
+where
+    T: IntoResponse,
+    H: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    fn into_response(self) -> Response {
+        let (status, headers, response) = self;
+        let mut response = response.into_response().status(status);
+
+        for (name, value) in headers {
+            response = response.header(name.as_ref(), value.as_ref());
+        }
+
+        response
+    }
+}
+
+/// Respond with either the success or the error response.
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(response) => response.into_response(),
+            Err(error) => error.into_response(),
+        }
+    }
+}
+
+/// Respond with a 404 Not Found if there's no value.
+impl<T: IntoResponse> IntoResponse for Option<T> {
+    fn into_response(self) -> Response {
+        self.map_or_else(Response::not_found, IntoResponse::into_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_strings_into_response() {
+        let response = "Hello, World!".into_response();
+        assert_eq!(response.body, Body::Text("Hello, World!".to_string()));
+
+        let response = "Hello, World!".to_string().into_response();
+        assert_eq!(response.body, Body::Text("Hello, World!".to_string()));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/plain".to_string())
+        );
+    }
+
+    #[test]
+    fn test_bytes_into_response() {
+        let response = vec![0, 1, 2].into_response();
+
+        assert_eq!(response.body, Body::Bytes(vec![0, 1, 2]));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"application/octet-stream".to_string())
+        );
+    }
+
+    #[test]
+    fn test_json_into_response() {
+        let response = json!({ "message": "Hello" }).into_response();
+
+        assert_eq!(response.body, Body::Json(json!({ "message": "Hello" })));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"application/json".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tuples_into_response() {
+        let response = (StatusCode::BadRequest, "Nope").into_response();
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+        assert_eq!(response.body, Body::Text("Nope".to_string()));
+
+        let response = (
+            StatusCode::NotFound,
+            HashMap::from([("X-Example".to_string(), "test".to_string())]),
+            "Missing",
+        )
+            .into_response();
+        assert_eq!(response.status_code, StatusCode::NotFound);
+        assert_eq!(response.headers.get("X-Example"), Some(&"test".to_string()));
+        assert_eq!(response.body, Body::Text("Missing".to_string()));
+    }
+
+    #[test]
+    fn test_result_into_response() {
+        let ok: Result<&str, StatusCode> = Ok("Hello");
+        assert_eq!(ok.into_response().status_code, StatusCode::Ok);
+
+        let err: Result<&str, StatusCode> = Err(StatusCode::BadRequest);
+        assert_eq!(err.into_response().status_code, StatusCode::BadRequest);
+    }
+
+    #[test]
+    fn test_option_into_response() {
+        assert_eq!(Some("Hello").into_response().status_code, StatusCode::Ok);
+        assert_eq!(
+            None::<&str>.into_response().status_code,
+            StatusCode::NotFound
+        );
+    }
+}
@@ -1,5 +1,24 @@
+mod date;
+mod into_response;
+mod parser;
 mod request;
 mod response;
+mod rewind;
+mod sse;
+mod status;
+mod stream;
+mod upgrade;
 
-pub use request::{Body, Method, Request};
-pub use response::{Response, StatusCode};
+pub use into_response::IntoResponse;
+#[cfg(feature = "http2")]
+pub use parser::signalled;
+pub use parser::{ParseError, Parser};
+#[cfg(feature = "http2")]
+pub use request::parse_query;
+pub use request::{Body, Method, Request, Version};
+pub use response::Response;
+pub use rewind::Rewind;
+pub use sse::{Event, Sse};
+pub use status::{CustomStatus, StatusCode};
+pub use stream::BodyStream;
+pub use upgrade::{OnUpgrade, Upgraded};
@@ -0,0 +1,667 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::watch,
+    time::timeout,
+};
+
+use super::{
+    request::{find, parse_header},
+    Request, StatusCode,
+};
+use crate::{config::Config, error::Error};
+
+/// The number of bytes to read from the connection at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// An error encountered while reading a request from a connection.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The connection failed, or was closed in the middle of a request.
+    Io(io::Error),
+    /// The request was malformed or exceeded a limit, and should be answered with an error response.
+    Rejected(Error),
+    /// The client sent the HTTP/2 connection preface instead of a request, so the connection should be served over HTTP/2.
+    /// Holds the bytes read from the connection so far, starting with the preface.
+    #[cfg(feature = "http2")]
+    Http2(Vec<u8>),
+}
+
+impl ParseError {
+    /// Reject the request with the given status code.
+    fn rejected(status: StatusCode, message: &'static str) -> Self {
+        Self::Rejected(Error::new(status, message))
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            ErrorKind::InvalidData => {
+                Self::Rejected(Error::from(error).with_status(StatusCode::BadRequest))
+            }
+            ErrorKind::Unsupported => {
+                Self::Rejected(Error::from(error).with_status(StatusCode::NotImplemented))
+            }
+            ErrorKind::TimedOut => {
+                Self::Rejected(Error::from(error).with_status(StatusCode::RequestTimeout))
+            }
+            _ => Self::Io(error),
+        }
+    }
+}
+
+/// Reads requests from a connection, across as many reads as needed.
+///
+/// Bytes read past the end of a request are kept around, and used for the next one,
+/// so pipelined requests arriving in the same read aren't lost.
+#[derive(Debug)]
+pub struct Parser {
+    /// Bytes read from the connection that haven't been consumed yet.
+    buf: Vec<u8>,
+    /// The limits applied to requests.
+    config: Arc<Config>,
+    /// Set once the server starts shutting down, to stop waiting for new requests.
+    shutdown: Option<watch::Receiver<bool>>,
+}
+
+impl Parser {
+    /// Create a parser with an empty buffer.
+    pub const fn new(config: Arc<Config>) -> Self {
+        Self {
+            buf: Vec::new(),
+            config,
+            shutdown: None,
+        }
+    }
+
+    /// Stop waiting for new requests once `shutdown` is set.
+    /// Requests that have already started arriving are still read in full.
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
+    /// Consume the parser, returning the bytes it read past the last request.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Read the next request from the connection.
+    /// Returns `None` if the connection was closed before a new request started.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the connection fails or times out, or if the request is malformed or exceeds the configured limits.
+    pub async fn next<S>(&mut self, stream: &mut S) -> Result<Option<Request>, ParseError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let config = self.config.clone();
+        let Some(head_end) = self.read_head(stream).await? else {
+            return Ok(None);
+        };
+
+        // Clients with prior knowledge of HTTP/2 start with its connection preface,
+        // which looks like the head of an HTTP/1 request (RFC 9113, section 3.4).
+        #[cfg(feature = "http2")]
+        if self.buf[..head_end] == *b"PRI * HTTP/2.0" {
+            return Err(ParseError::Http2(std::mem::take(&mut self.buf)));
+        }
+
+        let mut request = Request::parse_head(&self.buf[..head_end])?;
+        self.buf.drain(..head_end + 4);
+
+        if request.headers.len() > config.max_headers {
+            return Err(ParseError::rejected(
+                StatusCode::RequestHeaderFieldsTooLarge,
+                "Too many headers",
+            ));
+        }
+
+        let body = if let Some(encoding) = request.headers.get("transfer-encoding") {
+            if request.headers.contains_key("content-length") {
+                return Err(ParseError::rejected(
+                    StatusCode::BadRequest,
+                    "Both Content-Length and Transfer-Encoding were sent",
+                ));
+            }
+
+            // Chunked must be the last coding applied to a request body (RFC 9112, section 6.3).
+            let codings = encoding.split(',').map(str::trim).collect::<Vec<_>>();
+            if !codings
+                .last()
+                .is_some_and(|coding| coding.eq_ignore_ascii_case("chunked"))
+            {
+                return Err(ParseError::rejected(
+                    StatusCode::BadRequest,
+                    "Request body must use the chunked transfer coding",
+                ));
+            }
+            if codings.len() > 1 {
+                return Err(ParseError::rejected(
+                    StatusCode::NotImplemented,
+                    "Unsupported transfer coding",
+                ));
+            }
+
+            self.continue_if_expected(stream, &request).await?;
+            Some(self.read_chunked(stream, &mut request.trailers).await?)
+        } else if let Some(length) = request.content_length()? {
+            if length > config.max_body_size {
+                return Err(ParseError::rejected(
+                    StatusCode::ContentTooLarge,
+                    "Request body too large",
+                ));
+            }
+
+            if self.buf.len() < length {
+                self.continue_if_expected(stream, &request).await?;
+            }
+
+            self.fill(stream, length).await?;
+            Some(self.buf.drain(..length).collect::<Vec<_>>())
+        } else {
+            None
+        };
+
+        if let Some(body) = body {
+            request.body = request.parse_body(body)?;
+        }
+
+        Ok(Some(request))
+    }
+
+    /// Read until the end of the request line and headers, returning its position in the buffer.
+    /// Returns `None` if the connection was closed (or went idle, or the server is shutting down) before any bytes were received.
+    async fn read_head<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<Option<usize>, ParseError> {
+        loop {
+            // Servers should ignore empty lines received before the request line (RFC 9112, section 2.2).
+            let empty_lines = self
+                .buf
+                .iter()
+                .take_while(|byte| matches!(byte, b'\r' | b'\n'))
+                .count();
+            self.buf.drain(..empty_lines);
+
+            let head_end = find(&self.buf, b"\r\n\r\n");
+
+            if head_end.unwrap_or(self.buf.len()) > self.config.max_head_size {
+                return Err(ParseError::rejected(
+                    StatusCode::RequestHeaderFieldsTooLarge,
+                    "Request headers too large",
+                ));
+            }
+
+            if head_end.is_some() {
+                return Ok(head_end);
+            }
+
+            let read = match self.shutdown.clone() {
+                Some(mut shutdown) if self.buf.is_empty() => tokio::select! {
+                    read = self.read(stream) => read,
+                    () = signalled(&mut shutdown) => return Ok(None),
+                },
+                _ => self.read(stream).await,
+            };
+
+            match read {
+                Ok(()) => {}
+                Err(e)
+                    if matches!(e.kind(), ErrorKind::UnexpectedEof | ErrorKind::TimedOut)
+                        && self.buf.is_empty() =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Tell the client to go ahead and send the body, if it's waiting for confirmation.
+    async fn continue_if_expected<S: AsyncWrite + Unpin>(
+        &self,
+        stream: &mut S,
+        request: &Request,
+    ) -> io::Result<()> {
+        let expects_continue = request
+            .headers
+            .get("expect")
+            .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"));
+
+        if expects_continue {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read and decode a chunked body, storing any trailer fields in `trailers`.
+    ///
+    /// See [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-7.1) for more information.
+    async fn read_chunked<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+        trailers: &mut HashMap<String, String>,
+    ) -> Result<Vec<u8>, ParseError> {
+        let config = self.config.clone();
+        let mut body = Vec::new();
+
+        loop {
+            let line = self.read_line(stream, config.max_head_size).await?;
+            let size = parse_chunk_size(&line).ok_or_else(|| {
+                ParseError::rejected(StatusCode::BadRequest, "Invalid chunk size")
+            })?;
+
+            if size == 0 {
+                break;
+            }
+
+            if size > config.max_body_size - body.len() {
+                return Err(ParseError::rejected(
+                    StatusCode::ContentTooLarge,
+                    "Request body too large",
+                ));
+            }
+
+            self.fill(stream, size + 2).await?;
+            if &self.buf[size..size + 2] != b"\r\n" {
+                return Err(ParseError::rejected(
+                    StatusCode::BadRequest,
+                    "Chunk data is not followed by CRLF",
+                ));
+            }
+
+            body.extend(self.buf.drain(..size));
+            self.buf.drain(..2);
+        }
+
+        let mut trailers_size = 0;
+        loop {
+            let line = self.read_line(stream, config.max_head_size).await?;
+            if line.is_empty() {
+                return Ok(body);
+            }
+
+            trailers_size += line.len();
+            if trailers_size > config.max_head_size || trailers.len() >= config.max_headers {
+                return Err(ParseError::rejected(
+                    StatusCode::RequestHeaderFieldsTooLarge,
+                    "Request trailers too large",
+                ));
+            }
+
+            let line = std::str::from_utf8(&line)
+                .map_err(|_| ParseError::rejected(StatusCode::BadRequest, "Invalid trailer"))?;
+            let (name, value) = parse_header(line)?;
+            trailers.insert(name, value);
+        }
+    }
+
+    /// Read a line terminated by CRLF, returning it without the terminator.
+    async fn read_line<S: AsyncRead + Unpin>(
+        &mut self,
+        stream: &mut S,
+        limit: usize,
+    ) -> Result<Vec<u8>, ParseError> {
+        loop {
+            if let Some(end) = find(&self.buf, b"\r\n") {
+                let line = self.buf.drain(..end).collect();
+                self.buf.drain(..2);
+
+                return Ok(line);
+            }
+
+            if self.buf.len() > limit {
+                return Err(ParseError::rejected(
+                    StatusCode::BadRequest,
+                    "Line too long",
+                ));
+            }
+
+            self.read(stream).await?;
+        }
+    }
+
+    /// Read until the buffer holds at least `len` bytes.
+    async fn fill<S: AsyncRead + Unpin>(&mut self, stream: &mut S, len: usize) -> io::Result<()> {
+        while self.buf.len() < len {
+            self.read(stream).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read some more bytes from the connection into the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Will return an [`ErrorKind::UnexpectedEof`] error if the connection was closed,
+    /// or an [`ErrorKind::TimedOut`] error if nothing was received within the idle timeout.
+    async fn read<S: AsyncRead + Unpin>(&mut self, stream: &mut S) -> io::Result<()> {
+        self.buf.reserve(READ_SIZE);
+
+        let read = timeout(self.config.idle_timeout, stream.read_buf(&mut self.buf))
+            .await
+            .map_err(|_| io::Error::from(ErrorKind::TimedOut))?;
+
+        if read? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait until `shutdown` is set. Never resolves if it's dropped without being set.
+pub async fn signalled(shutdown: &mut watch::Receiver<bool>) {
+    while !*shutdown.borrow_and_update() {
+        if shutdown.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Parse the size of a chunk from its header line, ignoring any chunk extensions.
+fn parse_chunk_size(line: &[u8]) -> Option<usize> {
+    let line = std::str::from_utf8(line).ok()?;
+    let size = line.split(';').next()?.trim_end_matches([' ', '\t']);
+
+    if size.is_empty() || !size.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    usize::from_str_radix(size, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Body, Method};
+    use serde_json::json;
+    use tokio::io::duplex;
+
+    /// Parse a single request sent in the given chunks.
+    async fn parse(chunks: Vec<Vec<u8>>, config: Config) -> Result<Option<Request>, ParseError> {
+        let (mut client, mut server) = duplex(64 * 1024);
+
+        tokio::spawn(async move {
+            for chunk in chunks {
+                client.write_all(&chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+
+            client.shutdown().await.unwrap();
+        });
+
+        Parser::new(Arc::new(config)).next(&mut server).await
+    }
+
+    fn rejection(result: Result<Option<Request>, ParseError>) -> StatusCode {
+        match result {
+            Err(ParseError::Rejected(error)) => error.status().clone(),
+            result => panic!("expected the request to be rejected, got {result:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parses_request_split_across_reads() {
+        let body = "a".repeat(100_000);
+        let request = format!(
+            "POST /upload?name=test HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+
+        let chunks = request
+            .as_bytes()
+            .chunks(1000)
+            .map(<[u8]>::to_vec)
+            .collect();
+
+        let request = parse(chunks, Config::default()).await.unwrap().unwrap();
+
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(request.path, "/upload");
+        assert_eq!(request.query.get("name"), Some(&"test".to_string()));
+        assert_eq!(request.headers.get("host"), Some(&"localhost".to_string()));
+        assert_eq!(request.body, Body::Text(body));
+    }
+
+    #[tokio::test]
+    async fn test_parses_json_body() {
+        let request = parse(
+            vec![b"POST / HTTP/1.1\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: 17\r\n\r\n{\"name\":\"Miguel\"}".to_vec()],
+            Config::default(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(request.body, Body::Json(json!({ "name": "Miguel" })));
+    }
+
+    #[tokio::test]
+    async fn test_keeps_binary_body_intact() {
+        let body = [0, 159, 146, 150, 255];
+
+        for content_type in ["image/png", "text/plain"] {
+            let request = [
+                format!(
+                    "POST / HTTP/1.1\r\nContent-Type: {content_type}\r\nContent-Length: 5\r\n\r\n"
+                )
+                .as_bytes(),
+                &body,
+            ]
+            .concat();
+
+            let request = parse(vec![request], Config::default())
+                .await
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(request.body, Body::Bytes(body.to_vec()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parses_chunked_body() {
+        let request = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5;name=value\r\nhello\r\n7\r\n, world\r\n0\r\nChecksum: abc\r\nExpires: never\r\n\r\n";
+        let chunks = request.chunks(3).map(<[u8]>::to_vec).collect();
+
+        let request = parse(chunks, Config::default()).await.unwrap().unwrap();
+
+        assert_eq!(request.body, Body::Text("hello, world".to_string()));
+        assert_eq!(request.trailers.get("checksum"), Some(&"abc".to_string()));
+        assert_eq!(request.trailers.get("expires"), Some(&"never".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_chunked_bodies() {
+        for (body, status) in [
+            (&b"zz\r\nhello\r\n0\r\n\r\n"[..], StatusCode::BadRequest),
+            (b"+5\r\nhello\r\n0\r\n\r\n", StatusCode::BadRequest),
+            (b"5\r\nhello world\r\n0\r\n\r\n", StatusCode::BadRequest),
+            (
+                b"5\r\nhello\r\n0\r\nInvalid trailer\r\n\r\n",
+                StatusCode::BadRequest,
+            ),
+            (b"ffffffffffffffffffff\r\n", StatusCode::BadRequest),
+            (b"300000\r\n", StatusCode::ContentTooLarge),
+        ] {
+            let request = [
+                &b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"[..],
+                body,
+            ]
+            .concat();
+
+            assert_eq!(
+                rejection(parse(vec![request], Config::default()).await),
+                status
+            );
+        }
+
+        let both = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n";
+        assert_eq!(
+            rejection(parse(vec![both.to_vec()], Config::default()).await),
+            StatusCode::BadRequest
+        );
+
+        let gzip = b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n";
+        assert_eq!(
+            rejection(parse(vec![gzip.to_vec()], Config::default()).await),
+            StatusCode::NotImplemented
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keeps_bytes_for_the_next_request() {
+        let (mut client, mut server) = duplex(1024);
+        client
+            .write_all(
+                b"\r\nGET /one HTTP/1.1\r\n\r\nPOST /two HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi",
+            )
+            .await
+            .unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut parser = Parser::new(Arc::default());
+
+        let first = parser.next(&mut server).await.unwrap().unwrap();
+        assert_eq!(first.path, "/one");
+        assert_eq!(first.body, Body::None);
+
+        let second = parser.next(&mut server).await.unwrap().unwrap();
+        assert_eq!(second.path, "/two");
+        assert_eq!(second.body, Body::Text("hi".to_string()));
+
+        assert!(parser.next(&mut server).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sends_100_continue() {
+        let (mut client, mut server) = duplex(1024);
+        client
+            .write_all(b"PUT / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+            .await
+            .unwrap();
+
+        let parser = tokio::spawn(async move {
+            Parser::new(Arc::default())
+                .next(&mut server)
+                .await
+                .unwrap()
+                .unwrap()
+        });
+
+        let mut interim = [0; 25];
+        client.read_exact(&mut interim).await.unwrap();
+        assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        client.write_all(b"hello").await.unwrap();
+        assert_eq!(parser.await.unwrap().body, Body::Text("hello".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_requests_over_the_limits() {
+        let config = Config {
+            max_head_size: 64,
+            max_headers: 2,
+            max_body_size: 4,
+            ..Config::default()
+        };
+
+        let long_head = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(100));
+        assert_eq!(
+            rejection(parse(vec![long_head.into_bytes()], config.clone()).await),
+            StatusCode::RequestHeaderFieldsTooLarge
+        );
+
+        let many_headers = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n".to_vec();
+        assert_eq!(
+            rejection(parse(vec![many_headers], config.clone()).await),
+            StatusCode::RequestHeaderFieldsTooLarge
+        );
+
+        let large_body = b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello".to_vec();
+        assert_eq!(
+            rejection(parse(vec![large_body], config).await),
+            StatusCode::ContentTooLarge
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_malformed_requests() {
+        for request in [
+            &b"GET\r\n\r\n"[..],
+            b"GET / HTTP/1.1\r\nInvalid header\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: nope\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length : 5\r\n\r\nhello",
+            b"POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 6\r\n\r\nhello!",
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 1\r\n\r\n{",
+        ] {
+            assert_eq!(
+                rejection(parse(vec![request.to_vec()], Config::default()).await),
+                StatusCode::BadRequest
+            );
+        }
+
+        assert_eq!(
+            rejection(parse(vec![b"BREW / HTTP/1.1\r\n\r\n".to_vec()], Config::default()).await),
+            StatusCode::NotImplemented
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stops_waiting_for_requests_on_shutdown() {
+        let (shutdown, watcher) = watch::channel(false);
+        let (mut client, mut server) = duplex(1024);
+        let mut parser = Parser::new(Arc::default()).with_shutdown(watcher);
+
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let request = tokio::spawn(async move { parser.next(&mut server).await });
+
+        tokio::task::yield_now().await;
+        shutdown.send(true).unwrap();
+        client.write_all(b"\r\n").await.unwrap();
+
+        assert_eq!(request.await.unwrap().unwrap().unwrap().path, "/");
+
+        // The connection is still open, but no request has started arriving.
+        let (_client, mut server) = duplex(1024);
+        let mut parser = Parser::new(Arc::default()).with_shutdown(shutdown.subscribe());
+
+        assert!(parser.next(&mut server).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_truncated_body() {
+        let result = parse(
+            vec![b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello".to_vec()],
+            Config::default(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ParseError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof));
+        assert!(parse(vec![], Config::default()).await.unwrap().is_none());
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn test_detects_http2_preface() {
+        let preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0";
+
+        match parse(vec![preface.to_vec()], Config::default()).await {
+            Err(ParseError::Http2(buffered)) => assert_eq!(buffered, preface),
+            result => panic!("expected the HTTP/2 preface to be detected, got {result:?}"),
+        }
+    }
+}
@@ -1,8 +1,15 @@
+use super::BodyStream;
+use crate::{
+    listener::ConnectionInfo,
+    state::{State, StateMap},
+};
 use serde_json::Value;
 use std::{
     collections::HashMap,
     fmt::{Display, Formatter},
-    io::Error,
+    io::{Error, ErrorKind},
+    str::FromStr,
+    sync::Arc,
 };
 
 const CRLF: &str = "\r\n";
@@ -24,15 +31,25 @@ pub enum Method {
     Delete,
 }
 
-impl From<String> for Method {
-    fn from(val: String) -> Self {
-        match val.as_str() {
-            "HEAD" => Self::Head,
-            "GET" => Self::Get,
-            "POST" => Self::Post,
-            "PUT" => Self::Put,
-            "DELETE" => Self::Delete,
-            _ => todo!(),
+/// Parse a method from its name.
+///
+/// # Errors
+///
+/// Will return an [`ErrorKind::Unsupported`] error if the method is not supported.
+impl FromStr for Method {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "HEAD" => Ok(Self::Head),
+            "GET" => Ok(Self::Get),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "DELETE" => Ok(Self::Delete),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Unsupported method {val}"),
+            )),
         }
     }
 }
@@ -49,6 +66,46 @@ impl Display for Method {
     }
 }
 
+/// The HTTP version of a request.
+///
+/// See [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-2.3) for more information.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Version {
+    /// HTTP/1.0
+    Http10,
+    /// HTTP/1.1
+    Http11,
+    /// HTTP/2, for requests received over HTTP/2 connections.
+    Http2,
+}
+
+/// Parse a version from the last part of a request line.
+///
+/// # Errors
+///
+/// Will return an [`ErrorKind::InvalidData`] error if the version is not HTTP/1.0 or HTTP/1.1.
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "HTTP/1.0" => Ok(Self::Http10),
+            "HTTP/1.1" => Ok(Self::Http11),
+            _ => Err(invalid("Unsupported HTTP version")),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http10 => write!(f, "HTTP/1.0"),
+            Self::Http11 => write!(f, "HTTP/1.1"),
+            Self::Http2 => write!(f, "HTTP/2"),
+        }
+    }
+}
+
 /// The HTTP Body of a request.
 ///
 /// See [RFC 7230](https://tools.ietf.org/html/rfc7230#section-3.3) for more information.
@@ -60,30 +117,65 @@ pub enum Body {
     Text(String),
     /// A deserialized application/json body.
     Json(Value),
+    /// A binary body, for content types that aren't text.
+    Bytes(Vec<u8>),
+    /// A body sent in chunks as it is produced. See [`BodyStream`] for more information.
+    Stream(BodyStream),
 }
 
 impl Body {
-    /// # Panics
+    /// Parse a body according to its content type.
     ///
-    /// Will panic if the content type is `application/json` and the body is not valid JSON.
-    #[must_use]
-    pub fn parse(body: String, content_type: Option<&String>) -> Self {
-        match content_type {
-            Some(content_type) => match content_type.as_str() {
-                "application/json" => Self::Json(serde_json::from_str(&body).unwrap()),
-                _ => Self::Text(body),
-            },
-            None => Self::Text(body),
+    /// JSON bodies are deserialized, and text bodies (or bodies without a content type) are kept as text
+    /// if they're valid UTF-8. Anything else is kept as raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the content type is `application/json` and the body is not valid JSON.
+    pub fn parse(body: Vec<u8>, content_type: Option<&String>) -> Result<Self, serde_json::Error> {
+        let media_type = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|media_type| media_type.trim().to_lowercase());
+
+        match media_type.as_deref() {
+            Some("application/json") => Ok(Self::Json(serde_json::from_slice(&body)?)),
+            Some(media_type) if !is_text(media_type) => Ok(Self::Bytes(body)),
+            _ => Ok(
+                String::from_utf8(body).map_or_else(|e| Self::Bytes(e.into_bytes()), Self::Text)
+            ),
+        }
+    }
+
+    /// The raw bytes of the body, as they should be sent over the wire.
+    /// Streaming bodies are written separately, so they have no bytes here.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::None | Self::Stream(_) => Vec::new(),
+            Self::Text(body) => body.as_bytes().to_vec(),
+            Self::Json(body) => body.to_string().into_bytes(),
+            Self::Bytes(body) => body.clone(),
         }
     }
 }
 
+/// Whether a media type holds text, rather than binary data.
+fn is_text(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || matches!(
+            media_type,
+            "application/x-www-form-urlencoded" | "application/xml" | "application/javascript"
+        )
+}
+
 impl Display for Body {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::None => write!(f, ""),
             Self::Text(body) => write!(f, "{body}"),
             Self::Json(body) => write!(f, "{body}"),
+            Self::Bytes(body) => write!(f, "{}", String::from_utf8_lossy(body)),
+            // Streams can only be read once, so they're written straight to the connection instead.
+            Self::Stream(_) => Ok(()),
         }
     }
 }
@@ -97,115 +189,228 @@ pub struct Request {
     pub method: Method,
     /// The path of the request.
     pub path: String,
+    /// The HTTP version of the request.
+    pub version: Version,
     /// The parsed query of the request.
     pub query: HashMap<String, String>,
+    /// The path parameters captured by the matched route.
+    pub params: HashMap<String, String>,
     /// The parsed headers of the request.
     pub headers: HashMap<String, String>,
     /// The body of the request.
     pub body: Body,
+    /// The trailer fields sent after a chunked body, if any.
+    pub trailers: HashMap<String, String>,
+    /// The application state registered on the server.
+    pub(crate) state: StateMap,
+    /// Information about the connection the request was received on.
+    pub(crate) connection: Arc<ConnectionInfo>,
 }
 
-/// Try to parse a request object from a buffer.
-///
-/// # Errors
-///
-/// Will return an error if the buffer is empty.
-///
-/// # Panics
-///
-/// Will panic if the request method is not valid.
-impl TryFrom<&[u8; 1024]> for Request {
-    type Error = Error;
+impl Request {
+    /// Get the application state of type `T` registered on the server, if any.
+    /// See [`State`] for more information.
+    #[must_use]
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
+        State::from_map(&self.state)
+    }
+
+    /// Information about the connection the request was received on, like the address of the client.
+    /// See [`ConnectionInfo`] for more information.
+    #[must_use]
+    pub fn connection(&self) -> &ConnectionInfo {
+        &self.connection
+    }
+
+    /// The verified certificate the client presented over TLS, if any.
+    /// See [`Tls::with_client_auth`](crate::Tls::with_client_auth) for more information.
+    #[cfg(feature = "tls")]
+    #[must_use]
+    pub fn peer_certificate(&self) -> Option<&crate::PeerCertificate> {
+        self.connection.tls.as_ref()?.peer_certificate.as_ref()
+    }
+
+    /// Parse the request line and headers of a request, leaving the body empty.
+    /// `head` should not include the empty line separating the headers from the body.
+    ///
+    /// # Errors
+    ///
+    /// Will return an [`ErrorKind::InvalidData`] error if the request is malformed,
+    /// or an [`ErrorKind::Unsupported`] error if the method is not supported.
+    pub(crate) fn parse_head(head: &[u8]) -> Result<Self, Error> {
+        let head = std::str::from_utf8(head).map_err(|_| invalid("Request is not valid UTF-8"))?;
+        let mut lines = head
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
+
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (Some(method), Some(uri), Some(version), None) = (
+            request_line.next(),
+            request_line.next(),
+            request_line.next(),
+            request_line.next(),
+        ) else {
+            return Err(invalid("Malformed request line"));
+        };
 
-    fn try_from(buf: &[u8; 1024]) -> Result<Self, Self::Error> {
-        let mut body = Body::None;
-        let mut headers = HashMap::new();
+        let method = method.parse::<Method>()?;
+        let version = version.parse::<Version>()?;
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let query = parse_query(query);
+
+        let mut headers = HashMap::<String, String>::new();
+        for line in lines {
+            let (name, value) = parse_header(line)?;
 
-        if buf[0] == 0 {
-            return Err(Error::new(std::io::ErrorKind::InvalidData, "Empty request"));
+            // Repeated framing headers could be read differently by a proxy in front of us,
+            // letting a request be smuggled inside another (RFC 9112, section 6.3).
+            let conflicting = match (name.as_str(), headers.get(&name)) {
+                ("content-length", Some(existing)) => *existing != value,
+                ("transfer-encoding", Some(_)) => true,
+                _ => false,
+            };
+            if conflicting {
+                return Err(invalid("Conflicting framing headers"));
+            }
+
+            headers.insert(name, value);
         }
 
-        let mut buff_read: usize = 2;
-        let mut lines = buf.split(|&byte| byte == b'\n');
+        Ok(Self {
+            method,
+            path: path.to_string(),
+            version,
+            query,
+            headers,
+            body: Body::None,
+            params: HashMap::new(),
+            trailers: HashMap::new(),
+            state: StateMap::default(),
+            connection: Arc::default(),
+        })
+    }
 
-        let request_line = lines.next().unwrap();
-        buff_read += request_line.len() + 1;
-        let mut request_line = request_line.split(|&byte| byte == b' ');
+    /// Whether the client wants to keep the connection open after this request.
+    /// HTTP/1.1 connections are persistent unless closed, and HTTP/1.0 ones need to opt in.
+    ///
+    /// See [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112#section-9.3) for more information.
+    pub(crate) fn keep_alive(&self) -> bool {
+        let has_option = |option: &str| {
+            self.headers.get("connection").is_some_and(|connection| {
+                connection
+                    .split(',')
+                    .any(|value| value.trim().eq_ignore_ascii_case(option))
+            })
+        };
 
-        let method: Method = String::from_utf8_lossy(request_line.next().unwrap())
-            .to_string()
-            .into();
+        match self.version {
+            Version::Http11 => !has_option("close"),
+            Version::Http10 => has_option("keep-alive") && !has_option("close"),
+            // HTTP/2 connections don't use the `Connection` header, and stay open until either side closes them.
+            Version::Http2 => true,
+        }
+    }
 
-        let uri = String::from_utf8_lossy(request_line.next().unwrap());
-        let mut uri = uri.splitn(2, |byte| byte == '?');
+    /// The value of the `Content-Length` header, if any.
+    ///
+    /// # Errors
+    ///
+    /// Will return an [`ErrorKind::InvalidData`] error if the header is not a valid length.
+    pub(crate) fn content_length(&self) -> Result<Option<usize>, Error> {
+        self.headers
+            .get("content-length")
+            .map(|length| {
+                length
+                    .parse()
+                    .map_err(|_| invalid("Invalid Content-Length"))
+            })
+            .transpose()
+    }
 
-        let path = uri.next().unwrap().trim().to_string();
+    /// Parse a body for this request according to its content type.
+    ///
+    /// # Errors
+    ///
+    /// Will return an [`ErrorKind::InvalidData`] error if the body doesn't match its content type.
+    pub(crate) fn parse_body(&self, body: Vec<u8>) -> Result<Body, Error> {
+        Body::parse(body, self.headers.get("content-type"))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
 
-        let query = uri.next().map_or_else(HashMap::new, |query| {
-            query
-                .trim()
-                .split('&')
-                .map(|pair| {
-                    let mut pair = pair.split('=');
-                    let key = pair.next().unwrap().trim().to_lowercase();
-                    let value = pair.next().unwrap().trim().to_string();
+/// Build an error for a malformed request.
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
 
-                    (key, value)
-                })
-                .collect::<HashMap<String, String>>()
-        });
+/// Find the first occurrence of `needle` in `haystack`.
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
 
-        for line in lines {
-            if line == b"\r" {
-                break;
-            }
+/// Parse a query string into its lowercase keys and their values.
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            (key.trim().to_lowercase(), value.trim().to_string())
+        })
+        .collect()
+}
 
-            let mut header = line.splitn(2, |&byte| byte == b':');
-            let name = header.next().unwrap();
-            let value = header.next().unwrap();
+/// Parse a header (or trailer) line into its lowercase name and its value.
+///
+/// # Errors
+///
+/// Will return an [`ErrorKind::InvalidData`] error if the line isn't a valid header.
+pub fn parse_header(line: &str) -> Result<(String, String), Error> {
+    let (name, value) = line
+        .split_once(':')
+        .ok_or_else(|| invalid("Malformed header"))?;
 
-            let value = String::from_utf8_lossy(value).trim().to_string();
-            let name = String::from_utf8_lossy(name)
-                .trim()
-                .to_lowercase()
-                .to_string();
+    if name.is_empty() || name.trim() != name {
+        return Err(invalid("Malformed header name"));
+    }
 
-            headers.insert(name, value);
-            buff_read += line.len() + 1;
-        }
+    Ok((name.to_lowercase(), value.trim().to_string()))
+}
 
-        if let Some(content_length) = headers.get("content-length") {
-            let content_length = content_length.parse::<usize>().unwrap();
+/// Try to parse a request object from a buffer containing the whole request.
+///
+/// # Errors
+///
+/// Will return an error if the buffer does not contain a complete and valid request.
+impl TryFrom<&[u8]> for Request {
+    type Error = Error;
+
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        let end = find(buf, b"\r\n\r\n").ok_or_else(|| invalid("Incomplete request"))?;
+        let mut request = Self::parse_head(&buf[..end])?;
+
+        if let Some(length) = request.content_length()? {
+            let body = buf
+                .get(end + 4..end + 4 + length)
+                .ok_or_else(|| invalid("Incomplete body"))?;
 
-            body = Body::parse(
-                String::from_utf8_lossy(&buf[buff_read..buff_read + content_length])
-                    .trim()
-                    .to_string(),
-                headers.get("content-type"),
-            );
+            request.body = request.parse_body(body.to_vec())?;
         }
 
-        Ok(Self {
-            method,
-            path,
-            query,
-            headers,
-            body,
-        })
+        Ok(request)
     }
 }
 
 impl Display for Request {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut str_request = String::new();
-
-        str_request.push_str(&format!("{} {} HTTP/1.1{CRLF}", self.method, self.path));
+        write!(f, "{} {} {}{CRLF}", self.method, self.path, self.version)?;
         for (name, value) in &self.headers {
-            str_request.push_str(&format!("{name}: {value}{CRLF}"));
+            write!(f, "{name}: {value}{CRLF}")?;
         }
-        str_request.push_str(CRLF);
-        str_request.push_str(&self.body.to_string());
 
-        write!(f, "{str_request}")
+        write!(f, "{CRLF}{}", self.body)
     }
 }
@@ -1,42 +1,11 @@
-use crate::{Body, Method};
+use super::{date, IntoResponse, OnUpgrade, Upgraded};
+use crate::{error::Error, Body, BodyStream, Method, StatusCode, Version};
 use serde_json::Value;
-use std::{
-    collections::HashMap,
-    fmt::{Display, Formatter},
-};
+use std::{collections::HashMap, fmt::Write as _, future::Future, io, sync::Arc, time::SystemTime};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 const CRLF: &str = "\r\n";
 
-/// The HTTP status code of a response.
-///
-/// See [RFC 7231](https://tools.ietf.org/html/rfc7231#section-6) for more information.
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub enum StatusCode {
-    /// 200 OK
-    Ok,
-    /// 404 Not Found
-    NotFound,
-    /// 400 Bad Request
-    BadRequest,
-    /// 405 Method Not Allowed
-    MethodNotAllowed,
-}
-
-impl Display for StatusCode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::Ok => "200 OK",
-                Self::NotFound => "404 Not Found",
-                Self::BadRequest => "400 Bad Request",
-                Self::MethodNotAllowed => "405 Method Not Allowed",
-            }
-        )
-    }
-}
-
 /// An HTTP 1.1 response.
 ///
 /// See [RFC 2616](https://www.rfc-editor.org/rfc/rfc2616#section-6) for more information.
@@ -48,6 +17,10 @@ pub struct Response {
     pub headers: HashMap<String, String>,
     /// The body of the response.
     pub body: Body,
+    /// The error this response was built from, if any.
+    pub(crate) error: Option<Arc<Error>>,
+    /// What to do with the connection once this response switches protocols, if anything.
+    pub(crate) upgrade: Option<OnUpgrade>,
 }
 
 impl Response {
@@ -70,6 +43,8 @@ impl Response {
             body: Body::None,
             status_code: StatusCode::Ok,
             headers: HashMap::new(),
+            error: None,
+            upgrade: None,
         }
     }
 
@@ -116,6 +91,48 @@ impl Response {
             .body(Body::Json(body.clone()))
     }
 
+    /// Create a new binary response with the given content.
+    ///
+    /// # Example
+    /// ```
+    /// use lil_http::Response;
+    /// # use lil_http::{StatusCode, Body};
+    ///
+    /// let response = Response::bytes(&[0xde, 0xad, 0xbe, 0xef]);
+    ///
+    /// # assert_eq!(response.status_code, StatusCode::Ok);
+    /// # assert_eq!(response.body, Body::Bytes(vec![0xde, 0xad, 0xbe, 0xef]));
+    /// # assert_eq!(response.headers.get("Content-Type"), Some(&"application/octet-stream".to_string()));
+    /// ```
+    #[must_use]
+    pub fn bytes(body: &[u8]) -> Self {
+        Self::ok()
+            .header("Content-Type", "application/octet-stream")
+            .body(Body::Bytes(body.to_vec()))
+    }
+
+    /// Create a response that streams its body as it is produced.
+    /// See [`BodyStream`] for more information.
+    ///
+    /// # Example
+    /// ```
+    /// use lil_http::{BodyStream, Response};
+    /// # use lil_http::{StatusCode, Body};
+    ///
+    /// let (sender, body) = BodyStream::channel(16);
+    /// let response = Response::stream(body.clone());
+    ///
+    /// # assert_eq!(response.status_code, StatusCode::Ok);
+    /// # assert_eq!(response.body, Body::Stream(body));
+    /// # assert_eq!(response.headers.get("Content-Type"), Some(&"application/octet-stream".to_string()));
+    /// ```
+    #[must_use]
+    pub fn stream(body: BodyStream) -> Self {
+        Self::ok()
+            .header("Content-Type", "application/octet-stream")
+            .body(Body::Stream(body))
+    }
+
     /// Create a 404 Not Found response.
     ///
     /// # Example
@@ -152,6 +169,24 @@ impl Response {
         Self::text("Invalid Request").status(StatusCode::BadRequest)
     }
 
+    /// Create a 500 Internal Server Error response.
+    ///
+    /// # Example
+    /// ```
+    /// use lil_http::Response;
+    /// # use lil_http::{StatusCode, Body};
+    ///
+    /// let response = Response::internal_server_error();
+    ///
+    /// # assert_eq!(response.status_code, StatusCode::InternalServerError);
+    /// # assert_eq!(response.body, Body::Text("Internal Server Error".to_string()));
+    /// # assert_eq!(response.headers.get("Content-Type"), Some(&"text/plain".to_string()));
+    /// ```
+    #[must_use]
+    pub fn internal_server_error() -> Self {
+        Self::text("Internal Server Error").status(StatusCode::InternalServerError)
+    }
+
     /// Create a 405 Method Not Allowed response.
     /// The `methods` parameter is a list of allowed methods.
     /// The `Allow` header will be set to a comma-separated list of the methods.
@@ -180,6 +215,13 @@ impl Response {
             .header("Allow", &methods.join(", "))
     }
 
+    /// Get the error this response was built from, if the handler returned one.
+    /// This lets middleware (for example, a logger) inspect the original error.
+    #[must_use]
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_deref()
+    }
+
     /// Set the status code of the response.
     ///
     /// # Example
@@ -216,6 +258,48 @@ impl Response {
         self.clone()
     }
 
+    /// Switch the connection to another protocol once the response has been sent,
+    /// handing it over to `callback`. The status code is set to 101 Switching Protocols.
+    ///
+    /// The connection is closed once the callback finishes (or drops it), instead of serving more requests.
+    /// Only HTTP/1.1 connections can be upgraded: other clients get a 505 HTTP Version Not Supported response instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::{Response, Server};
+    /// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    ///
+    /// # async fn example() {
+    /// let mut http = Server::new().await.unwrap();
+    ///
+    /// http.routes.get("/tunnel", || {
+    ///     Response::ok()
+    ///         .header("Upgrade", "echo")
+    ///         .header("Connection", "Upgrade")
+    ///         .on_upgrade(|mut io| async move {
+    ///             let mut buf = [0; 1024];
+    ///             while let Ok(read @ 1..) = io.read(&mut buf).await {
+    ///                 if io.write_all(&buf[..read]).await.is_err() {
+    ///                     break;
+    ///                 }
+    ///             }
+    ///         })
+    /// });
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn on_upgrade<F, Fut>(&mut self, callback: F) -> Self
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.status_code = StatusCode::SwitchingProtocols;
+        self.upgrade = Some(OnUpgrade::new(callback));
+
+        self.clone()
+    }
+
     /// Set the body of the response.
     ///
     /// # Example
@@ -237,19 +321,151 @@ impl Response {
     }
 }
 
-/// Convert a Response object into a HTTP 1.1 response string.
-impl ToString for Response {
-    fn to_string(&self) -> String {
-        let mut str_response = String::new();
+impl Response {
+    /// The status line and headers of the response, without the empty line that ends them.
+    fn head(&self) -> String {
+        let mut head = String::new();
 
-        str_response.push_str(&format!("HTTP/1.1 {}{CRLF}", self.status_code));
+        _ = write!(head, "HTTP/1.1 {}{CRLF}", self.status_code);
         for (name, value) in &self.headers {
-            str_response.push_str(&format!("{name}: {value}{CRLF}"));
+            _ = write!(head, "{name}: {value}{CRLF}");
+        }
+
+        head
+    }
+
+    /// Serialize the response into the bytes of an HTTP 1.1 response.
+    /// A `Content-Length` header is added unless one was set explicitly.
+    /// Streaming bodies are left out, since they can only be written to a connection, as are bodies of
+    /// responses whose status code doesn't allow one (1xx, 204 and 304).
+    ///
+    /// # Example
+    /// ```
+    /// use lil_http::Response;
+    ///
+    /// let response = Response::text("Hello, World!");
+    ///
+    /// assert_eq!(
+    ///     response.to_bytes(),
+    ///     b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 13\r\n\r\nHello, World!"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.serialize(true)
+    }
+
+    /// Serialize the response, leaving the body out (but not its headers) unless `with_body` is set.
+    fn serialize(&self, with_body: bool) -> Vec<u8> {
+        let body = self.body.to_bytes();
+        let mut head = self.head();
+
+        if self.allows_body() && self.header_value("Content-Length").is_none() {
+            _ = write!(head, "Content-Length: {}{CRLF}", body.len());
+        }
+
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(CRLF.as_bytes());
+        if with_body && self.allows_body() {
+            bytes.extend_from_slice(&body);
+        }
+
+        bytes
+    }
+
+    /// Get the value of a header, regardless of the case it was set with.
+    pub(crate) fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the connection should be handed over once this response has been sent.
+    pub(crate) const fn is_upgrade(&self) -> bool {
+        self.upgrade.is_some() && self.status_code.as_u16() == 101
+    }
+
+    /// Replace a response switching protocols with an error, unless the client is using HTTP/1.1.
+    /// See [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-7.8) for more information.
+    pub(crate) fn check_upgrade(self, version: Version) -> Self {
+        if self.is_upgrade() && version != Version::Http11 {
+            return Error::new(
+                StatusCode::HttpVersionNotSupported,
+                "switching protocols requires HTTP/1.1",
+            )
+            .into_response();
+        }
+
+        self
+    }
+
+    /// Whether a client using `version` can tell where the body ends without the connection being closed.
+    pub(crate) fn is_framed(&self, version: Version) -> bool {
+        match &self.body {
+            Body::Stream(stream) if self.allows_body() => {
+                stream.length().is_some()
+                    || self.header_value("Content-Length").is_some()
+                    || version == Version::Http11
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether the status code of the response allows it to have a body.
+    /// See [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-8.6) for more information.
+    pub(crate) const fn allows_body(&self) -> bool {
+        !matches!(self.status_code.as_u16(), 100..=199 | 204 | 304)
+    }
+
+    /// Add the `Date` header, and the `Server` header if a name is given, unless they were set explicitly.
+    pub(crate) fn add_default_headers(&mut self, server: Option<&str>) {
+        if self.header_value("Date").is_none() {
+            self.headers
+                .insert("Date".to_string(), date::format(SystemTime::now()));
         }
-        str_response.push_str(CRLF);
-        str_response.push_str(&self.body.to_string());
 
-        str_response
+        if let Some(server) = server.filter(|_| self.header_value("Server").is_none()) {
+            self.headers
+                .insert("Server".to_string(), server.to_string());
+        }
+    }
+
+    /// Write the response to a connection, streaming its body if needed.
+    /// Streams of unknown length are sent chunked to HTTP/1.1 clients, and until the connection closes otherwise,
+    /// unless the handler set a `Content-Length` header itself. Responses to `HEAD` requests (`is_head`) keep their headers, but leave the body out.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to the connection fails, or if a streaming body fails.
+    pub(crate) async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        version: Version,
+        is_head: bool,
+    ) -> io::Result<()> {
+        let Body::Stream(stream) = &self.body else {
+            return writer.write_all(&self.serialize(!is_head)).await;
+        };
+
+        let mut chunked = false;
+        let mut head = self.head();
+        if self.allows_body() && self.header_value("Content-Length").is_none() {
+            if let Some(length) = stream.length() {
+                _ = write!(head, "Content-Length: {length}{CRLF}");
+            } else if version == Version::Http11 {
+                chunked = true;
+                _ = write!(head, "Transfer-Encoding: chunked{CRLF}");
+            }
+        }
+        head.push_str(CRLF);
+
+        writer.write_all(head.as_bytes()).await?;
+        if is_head || !self.allows_body() {
+            return Ok(());
+        }
+
+        stream.write_to(writer, chunked).await
     }
 }
 
@@ -258,12 +474,113 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_response_to_string() {
+    fn test_response_to_bytes() {
         let response = Response::text("Hello, World!");
 
         assert_eq!(
-            response.to_string(),
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nHello, World!"
+            response.to_bytes(),
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 13\r\n\r\nHello, World!"
+        );
+
+        let response = Response::bytes(&[0, 159, 146, 150]);
+
+        assert_eq!(
+            response.to_bytes(),
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 4\r\n\r\n\x00\x9f\x92\x96"
+        );
+
+        let response = Response::ok().status(StatusCode::NoContent);
+        assert_eq!(response.to_bytes(), b"HTTP/1.1 204 No Content\r\n\r\n");
+
+        // Statuses that can't have a body never send one, or it would be read as the next response.
+        let response = Response::text("Hello").status(StatusCode::NoContent);
+        assert!(String::from_utf8(response.to_bytes())
+            .unwrap()
+            .ends_with("\r\n\r\n"));
+
+        let response = Response::text("Hello").header("Content-Length", "5");
+        assert_eq!(
+            String::from_utf8(response.to_bytes())
+                .unwrap()
+                .matches("Content-Length")
+                .count(),
+            1
         );
     }
+
+    #[test]
+    fn test_response_default_headers() {
+        let mut response = Response::ok();
+        response.add_default_headers(Some("lil-http"));
+
+        assert!(response.headers.get("Date").unwrap().ends_with(" GMT"));
+        assert_eq!(
+            response.headers.get("Server"),
+            Some(&"lil-http".to_string())
+        );
+
+        let mut response = Response::ok()
+            .header("date", "Thu, 01 Jan 1970 00:00:00 GMT")
+            .header("Server", "custom");
+        response.add_default_headers(Some("lil-http"));
+
+        assert_eq!(response.headers.len(), 2);
+        assert_eq!(response.headers.get("Server"), Some(&"custom".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_response_writes_stream() {
+        let response = Response::ok().body(Body::Stream(BodyStream::from_reader(&b"Hello"[..])));
+
+        let mut output = Vec::new();
+        response
+            .write_to(&mut output, Version::Http11, false)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            output,
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n\r\n"
+        );
+
+        let response = Response::ok().body(Body::Stream(
+            BodyStream::from_reader(&b"Hello"[..]).with_length(5),
+        ));
+
+        let mut output = Vec::new();
+        response
+            .write_to(&mut output, Version::Http11, false)
+            .await
+            .unwrap();
+
+        assert_eq!(output, b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello");
+    }
+
+    #[tokio::test]
+    async fn test_response_stream_framing() {
+        let response = Response::ok()
+            .header("Content-Length", "5")
+            .body(Body::Stream(BodyStream::from_reader(&b"Hello"[..])));
+
+        let mut output = Vec::new();
+        response
+            .write_to(&mut output, Version::Http11, false)
+            .await
+            .unwrap();
+
+        assert_eq!(output, b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello");
+        assert!(response.is_framed(Version::Http10));
+
+        let response = Response::ok()
+            .status(StatusCode::NoContent)
+            .body(Body::Stream(BodyStream::from_reader(&b"Hello"[..])));
+
+        let mut output = Vec::new();
+        response
+            .write_to(&mut output, Version::Http11, false)
+            .await
+            .unwrap();
+
+        assert_eq!(output, b"HTTP/1.1 204 No Content\r\n\r\n");
+    }
 }
@@ -0,0 +1,81 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// A stream that replays bytes that were already read from it, before reading from it again.
+#[derive(Debug)]
+pub struct Rewind<S> {
+    /// The bytes to replay, and how many of them have been replayed so far.
+    prefix: (Vec<u8>, usize),
+    /// The underlying stream.
+    inner: S,
+}
+
+impl<S> Rewind<S> {
+    /// Replay `prefix` before reading from `inner`.
+    pub const fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Self {
+            prefix: (prefix, 0),
+            inner,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Rewind<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let (prefix, replayed) = &mut self.prefix;
+
+        if *replayed < prefix.len() {
+            let remaining = &prefix[*replayed..];
+            let read = remaining.len().min(buf.remaining());
+
+            buf.put_slice(&remaining[..read]);
+            *replayed += read;
+
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Rewind<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_replays_rewound_bytes() {
+        let mut stream = Rewind::new(b"hello, ".to_vec(), &b"world"[..]);
+
+        let mut output = String::new();
+        stream.read_to_string(&mut output).await.unwrap();
+
+        assert_eq!(output, "hello, world");
+    }
+}
@@ -0,0 +1,328 @@
+use serde_json::Value;
+use std::{fmt::Write as _, time::Duration};
+use tokio::sync::mpsc;
+
+use super::{BodyStream, IntoResponse, Response};
+
+/// How often to send a comment when no events have been sent, by default.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A single server-sent event.
+/// See the [HTML standard](https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation) for how clients interpret each field.
+///
+/// # Example
+///
+/// ```
+/// use lil_http::Event;
+///
+/// let event = Event::new().event("update").id("42").data("first line\nsecond line");
+///
+/// assert_eq!(
+///     event.to_bytes(),
+///     b"id: 42\nevent: update\ndata: first line\ndata: second line\n\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Event {
+    /// The ID clients send back in `Last-Event-ID` when reconnecting.
+    id: Option<String>,
+    /// The type of the event, `message` if not set.
+    kind: Option<String>,
+    /// The data of the event.
+    data: Option<String>,
+    /// How long clients should wait before reconnecting.
+    retry: Option<Duration>,
+    /// A comment, which clients ignore.
+    comment: Option<String>,
+}
+
+impl Event {
+    /// Create an empty event.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the data of the event. Data spanning several lines is sent as several `data` fields.
+    #[must_use]
+    pub fn data(mut self, data: &str) -> Self {
+        self.data = Some(data.to_string());
+
+        self
+    }
+
+    /// Set the data of the event to the given JSON value.
+    #[must_use]
+    pub fn json(self, data: &Value) -> Self {
+        self.data(&data.to_string())
+    }
+
+    /// Set the type of the event, which clients can listen for separately.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the type contains a line break.
+    #[must_use]
+    pub fn event(mut self, event: &str) -> Self {
+        assert!(
+            !has_line_break(event),
+            "event types can't contain line breaks"
+        );
+        self.kind = Some(event.to_string());
+
+        self
+    }
+
+    /// Set the ID of the event, which clients send back in the `Last-Event-ID` header when reconnecting.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the ID contains a line break or a null character.
+    #[must_use]
+    pub fn id(mut self, id: &str) -> Self {
+        assert!(
+            !has_line_break(id) && !id.contains('\0'),
+            "event IDs can't contain line breaks or null characters"
+        );
+        self.id = Some(id.to_string());
+
+        self
+    }
+
+    /// Set how long clients should wait before reconnecting, if the connection is lost.
+    #[must_use]
+    pub const fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+
+        self
+    }
+
+    /// Add a comment to the event, which clients ignore.
+    #[must_use]
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+
+        self
+    }
+
+    /// Serialize the event into the `text/event-stream` format.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = String::new();
+        let mut field = |name: &str, value: &str| {
+            for line in value
+                .split("\r\n")
+                .flat_map(|line| line.split(['\r', '\n']))
+            {
+                _ = writeln!(output, "{name}: {line}");
+            }
+        };
+
+        if let Some(comment) = &self.comment {
+            field("", comment);
+        }
+        if let Some(id) = &self.id {
+            field("id", id);
+        }
+        if let Some(kind) = &self.kind {
+            field("event", kind);
+        }
+        if let Some(retry) = self.retry {
+            field("retry", &retry.as_millis().to_string());
+        }
+        if let Some(data) = &self.data {
+            field("data", data);
+        }
+
+        output.push('\n');
+        output.into_bytes()
+    }
+}
+
+/// Whether `value` contains a character that would end a field early.
+fn has_line_break(value: &str) -> bool {
+    value.contains(['\r', '\n'])
+}
+
+/// A `text/event-stream` response, sending events as they're produced.
+///
+/// Events are sent through the channel returned by [`Sse::channel`]. A comment is sent whenever no
+/// events have been sent for a while (every 15 seconds by default), so proxies don't close the
+/// connection and disconnects are noticed. Once the client disconnects, sending an event fails
+/// and [`Sender::closed`](mpsc::Sender::closed) resolves, so the producer can stop.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Event, Server, Sse};
+/// use std::time::Duration;
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.get("/clock", || {
+///     let (sender, sse) = Sse::channel(16);
+///
+///     tokio::spawn(async move {
+///         for tick in 0.. {
+///             let event = Event::new().event("tick").data(&tick.to_string());
+///             if sender.send(event).await.is_err() {
+///                 break; // The client disconnected.
+///             }
+///
+///             tokio::time::sleep(Duration::from_secs(1)).await;
+///         }
+///     });
+///
+///     sse
+/// });
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Sse {
+    /// Where the events come from.
+    receiver: mpsc::Receiver<Event>,
+    /// How often to send a comment when no events have been sent, if at all.
+    keep_alive: Option<Duration>,
+}
+
+impl Sse {
+    /// Create a stream of events fed by a channel, which ends when the sender is dropped.
+    /// `capacity` is the number of events that can be buffered before sending waits for the client.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `capacity` is zero.
+    #[must_use]
+    pub fn channel(capacity: usize) -> (mpsc::Sender<Event>, Self) {
+        let (sender, receiver) = mpsc::channel(capacity);
+
+        (
+            sender,
+            Self {
+                receiver,
+                keep_alive: Some(KEEP_ALIVE),
+            },
+        )
+    }
+
+    /// Set how often to send a comment when no events have been sent, or `None` to never send one.
+    #[must_use]
+    pub const fn with_keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval;
+
+        self
+    }
+}
+
+impl IntoResponse for Sse {
+    fn into_response(self) -> Response {
+        Response::stream(BodyStream::events(self.receiver, self.keep_alive))
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Body, Server};
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::oneshot,
+        time::timeout,
+    };
+
+    #[test]
+    fn test_serializes_events() {
+        let event = Event::new()
+            .comment("hello")
+            .retry(Duration::from_secs(3))
+            .json(&json!({ "a": 1 }));
+        assert_eq!(
+            event.to_bytes(),
+            b": hello\nretry: 3000\ndata: {\"a\":1}\n\n"
+        );
+
+        let event = Event::new().data("one\r\ntwo\rthree\n");
+        assert_eq!(
+            event.to_bytes(),
+            b"data: one\ndata: two\ndata: three\ndata: \n\n"
+        );
+    }
+
+    #[test]
+    #[should_panic = "event IDs can't contain line breaks or null characters"]
+    fn test_rejects_ids_with_line_breaks() {
+        let _ = Event::new().id("1\n2");
+    }
+
+    #[tokio::test]
+    async fn test_streams_events() {
+        let (sender, sse) = Sse::channel(1);
+        let response = sse
+            .with_keep_alive(Some(Duration::from_millis(50)))
+            .into_response();
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some(&"text/event-stream".to_string())
+        );
+
+        let Body::Stream(body) = response.body else {
+            panic!("expected a streaming body");
+        };
+        let mut chunks = body.chunks().unwrap();
+
+        sender.send(Event::new().data("hi")).await.unwrap();
+        assert_eq!(chunks.next().await.unwrap().unwrap(), b"data: hi\n\n");
+        assert_eq!(chunks.next().await.unwrap().unwrap(), b": keep-alive\n\n");
+
+        // Dropping the body (as the server does when the client disconnects) lets the producer know.
+        drop(chunks);
+        assert!(sender.send(Event::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notices_client_disconnects() {
+        let (done, stopped) = oneshot::channel();
+        let done = std::sync::Mutex::new(Some(done));
+
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.routes.get("/events", move || {
+            let (sender, sse) = Sse::channel(1);
+            let done = done.lock().unwrap().take();
+
+            tokio::spawn(async move {
+                sender.send(Event::new().data("hello")).await.unwrap();
+                sender.closed().await;
+                done.unwrap().send(()).unwrap();
+            });
+
+            sse.with_keep_alive(Some(Duration::from_millis(10)))
+        });
+        tokio::spawn(async move { http.run().await });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = Vec::new();
+        while !response.ends_with(b"data: hello\n\n\r\n") {
+            response.push(stream.read_u8().await.unwrap());
+        }
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.contains("Content-Type: text/event-stream\r\n"));
+        assert!(response.contains("Transfer-Encoding: chunked\r\n"));
+
+        drop(stream);
+        timeout(Duration::from_secs(5), stopped)
+            .await
+            .expect("the producer wasn't told about the disconnect")
+            .unwrap();
+    }
+}
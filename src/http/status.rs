@@ -0,0 +1,302 @@
+use std::{
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+macro_rules! status_codes {
+    ($(($code:literal, $name:ident, $phrase:literal),)+) => {
+        /// The HTTP status code of a response.
+        ///
+        /// Every status code registered with [IANA](https://www.iana.org/assignments/http-status-codes)
+        /// has its own variant. Non-standard codes can be created with [`StatusCode::custom`].
+        /// Status codes are compared by their numeric value, regardless of their reason phrase.
+        ///
+        /// See [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-15) for more information.
+        #[derive(Debug, Clone)]
+        pub enum StatusCode {
+            $(
+                #[doc = concat!(stringify!($code), " ", $phrase)]
+                $name,
+            )+
+            /// A non-standard status code, with a custom reason phrase.
+            Custom(CustomStatus),
+        }
+
+        impl StatusCode {
+            /// The numeric value of the status code.
+            ///
+            /// # Example
+            /// ```
+            /// use lil_http::StatusCode;
+            ///
+            /// assert_eq!(StatusCode::NotFound.as_u16(), 404);
+            /// ```
+            #[must_use]
+            pub const fn as_u16(&self) -> u16 {
+                match self {
+                    $(Self::$name => $code,)+
+                    Self::Custom(custom) => custom.code,
+                }
+            }
+
+            /// The reason phrase of the status code.
+            ///
+            /// # Example
+            /// ```
+            /// use lil_http::StatusCode;
+            ///
+            /// assert_eq!(StatusCode::NotFound.reason_phrase(), "Not Found");
+            /// ```
+            #[must_use]
+            pub fn reason_phrase(&self) -> &str {
+                match self {
+                    $(Self::$name => $phrase,)+
+                    Self::Custom(custom) => &custom.reason,
+                }
+            }
+
+            /// Get the status code for a numeric value.
+            /// Unregistered codes are returned as [`StatusCode::Custom`], with an empty reason phrase.
+            /// Returns `None` if the value is outside of the 100-599 range.
+            ///
+            /// # Example
+            /// ```
+            /// use lil_http::StatusCode;
+            ///
+            /// assert_eq!(StatusCode::from_u16(201), Some(StatusCode::Created));
+            /// assert_eq!(StatusCode::from_u16(299), Some(StatusCode::custom(299, "")));
+            /// assert_eq!(StatusCode::from_u16(42), None);
+            /// ```
+            #[must_use]
+            pub const fn from_u16(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$name),)+
+                    100..=599 => Some(Self::Custom(CustomStatus {
+                        code,
+                        reason: String::new(),
+                    })),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    (100, Continue, "Continue"),
+    (101, SwitchingProtocols, "Switching Protocols"),
+    (102, Processing, "Processing"),
+    (103, EarlyHints, "Early Hints"),
+    (200, Ok, "OK"),
+    (201, Created, "Created"),
+    (202, Accepted, "Accepted"),
+    (203, NonAuthoritativeInformation, "Non-Authoritative Information"),
+    (204, NoContent, "No Content"),
+    (205, ResetContent, "Reset Content"),
+    (206, PartialContent, "Partial Content"),
+    (207, MultiStatus, "Multi-Status"),
+    (208, AlreadyReported, "Already Reported"),
+    (226, ImUsed, "IM Used"),
+    (300, MultipleChoices, "Multiple Choices"),
+    (301, MovedPermanently, "Moved Permanently"),
+    (302, Found, "Found"),
+    (303, SeeOther, "See Other"),
+    (304, NotModified, "Not Modified"),
+    (305, UseProxy, "Use Proxy"),
+    (307, TemporaryRedirect, "Temporary Redirect"),
+    (308, PermanentRedirect, "Permanent Redirect"),
+    (400, BadRequest, "Bad Request"),
+    (401, Unauthorized, "Unauthorized"),
+    (402, PaymentRequired, "Payment Required"),
+    (403, Forbidden, "Forbidden"),
+    (404, NotFound, "Not Found"),
+    (405, MethodNotAllowed, "Method Not Allowed"),
+    (406, NotAcceptable, "Not Acceptable"),
+    (407, ProxyAuthenticationRequired, "Proxy Authentication Required"),
+    (408, RequestTimeout, "Request Timeout"),
+    (409, Conflict, "Conflict"),
+    (410, Gone, "Gone"),
+    (411, LengthRequired, "Length Required"),
+    (412, PreconditionFailed, "Precondition Failed"),
+    (413, ContentTooLarge, "Content Too Large"),
+    (414, UriTooLong, "URI Too Long"),
+    (415, UnsupportedMediaType, "Unsupported Media Type"),
+    (416, RangeNotSatisfiable, "Range Not Satisfiable"),
+    (417, ExpectationFailed, "Expectation Failed"),
+    (421, MisdirectedRequest, "Misdirected Request"),
+    (422, UnprocessableContent, "Unprocessable Content"),
+    (423, Locked, "Locked"),
+    (424, FailedDependency, "Failed Dependency"),
+    (425, TooEarly, "Too Early"),
+    (426, UpgradeRequired, "Upgrade Required"),
+    (428, PreconditionRequired, "Precondition Required"),
+    (429, TooManyRequests, "Too Many Requests"),
+    (431, RequestHeaderFieldsTooLarge, "Request Header Fields Too Large"),
+    (451, UnavailableForLegalReasons, "Unavailable For Legal Reasons"),
+    (500, InternalServerError, "Internal Server Error"),
+    (501, NotImplemented, "Not Implemented"),
+    (502, BadGateway, "Bad Gateway"),
+    (503, ServiceUnavailable, "Service Unavailable"),
+    (504, GatewayTimeout, "Gateway Timeout"),
+    (505, HttpVersionNotSupported, "HTTP Version Not Supported"),
+    (506, VariantAlsoNegotiates, "Variant Also Negotiates"),
+    (507, InsufficientStorage, "Insufficient Storage"),
+    (508, LoopDetected, "Loop Detected"),
+    (510, NotExtended, "Not Extended"),
+    (511, NetworkAuthenticationRequired, "Network Authentication Required"),
+}
+
+/// A non-standard status code, created with [`StatusCode::custom`].
+///
+/// Its fields are private, so the code and reason phrase are always validated.
+#[derive(Debug, Clone)]
+pub struct CustomStatus {
+    /// The numeric value of the status code.
+    code: u16,
+    /// The reason phrase sent in the status line.
+    reason: String,
+}
+
+impl StatusCode {
+    /// Create a non-standard status code with a custom reason phrase.
+    ///
+    /// # Example
+    /// ```
+    /// use lil_http::StatusCode;
+    ///
+    /// let status = StatusCode::custom(599, "Network Connect Timeout");
+    ///
+    /// assert_eq!(status.to_string(), "599 Network Connect Timeout");
+    /// assert!(status.is_server_error());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the code is outside of the 100-599 range, or if the reason phrase contains
+    /// control characters (other than tabs), which would let it break out of the status line.
+    #[must_use]
+    pub fn custom(code: u16, reason: &str) -> Self {
+        assert!(
+            (100..=599).contains(&code),
+            "status codes must be between 100 and 599"
+        );
+        assert!(
+            !reason.chars().any(|c| c.is_control() && c != '\t'),
+            "reason phrases can't contain control characters"
+        );
+
+        Self::Custom(CustomStatus {
+            code,
+            reason: reason.to_string(),
+        })
+    }
+
+    /// Whether the status code is informational (1xx).
+    #[must_use]
+    pub const fn is_informational(&self) -> bool {
+        matches!(self.as_u16(), 100..=199)
+    }
+
+    /// Whether the status code indicates success (2xx).
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        matches!(self.as_u16(), 200..=299)
+    }
+
+    /// Whether the status code is a redirection (3xx).
+    #[must_use]
+    pub const fn is_redirection(&self) -> bool {
+        matches!(self.as_u16(), 300..=399)
+    }
+
+    /// Whether the status code indicates a client error (4xx).
+    #[must_use]
+    pub const fn is_client_error(&self) -> bool {
+        matches!(self.as_u16(), 400..=499)
+    }
+
+    /// Whether the status code indicates a server error (5xx).
+    #[must_use]
+    pub const fn is_server_error(&self) -> bool {
+        matches!(self.as_u16(), 500..=599)
+    }
+}
+
+impl PartialEq for StatusCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_u16() == other.as_u16()
+    }
+}
+
+impl Eq for StatusCode {}
+
+impl Hash for StatusCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_u16().hash(state);
+    }
+}
+
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.as_u16(), self.reason_phrase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code_roundtrip() {
+        for code in 100..=599 {
+            let status = StatusCode::from_u16(code).unwrap();
+
+            assert_eq!(status.as_u16(), code);
+        }
+
+        assert_eq!(StatusCode::from_u16(99), None);
+        assert_eq!(StatusCode::from_u16(600), None);
+    }
+
+    #[test]
+    fn test_status_code_display() {
+        assert_eq!(StatusCode::Ok.to_string(), "200 OK");
+        assert_eq!(
+            StatusCode::TooManyRequests.to_string(),
+            "429 Too Many Requests"
+        );
+        assert_eq!(StatusCode::from_u16(299).unwrap().to_string(), "299 ");
+    }
+
+    #[test]
+    fn test_status_code_classification() {
+        assert!(StatusCode::Continue.is_informational());
+        assert!(StatusCode::NoContent.is_success());
+        assert!(StatusCode::MovedPermanently.is_redirection());
+        assert!(StatusCode::Conflict.is_client_error());
+        assert!(StatusCode::ServiceUnavailable.is_server_error());
+        assert!(!StatusCode::Ok.is_client_error());
+        assert!(StatusCode::custom(599, "Whatever").is_server_error());
+    }
+
+    #[test]
+    fn test_custom_status_codes_compare_by_value() {
+        assert_eq!(
+            StatusCode::custom(404, "Gone Fishing"),
+            StatusCode::NotFound
+        );
+        assert_ne!(StatusCode::custom(299, "Whatever"), StatusCode::Ok);
+    }
+
+    #[test]
+    #[should_panic(expected = "status codes must be between 100 and 599")]
+    fn test_custom_status_code_must_be_in_range() {
+        let _ = StatusCode::custom(600, "Nope");
+    }
+
+    #[test]
+    #[should_panic(expected = "reason phrases can't contain control characters")]
+    fn test_custom_reason_phrase_cant_contain_line_breaks() {
+        let _ = StatusCode::custom(299, "Nope\r\nSet-Cookie: a=b");
+    }
+}
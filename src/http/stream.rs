@@ -0,0 +1,304 @@
+use std::{
+    fmt::{Debug, Formatter},
+    io::{self, ErrorKind},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+    time::sleep,
+};
+
+use super::Event;
+
+/// The number of bytes to read from a reader at a time.
+const READ_SIZE: usize = 8 * 1024;
+
+/// Where the chunks of a streaming body come from.
+enum Source {
+    /// Chunks sent through a channel.
+    Channel(mpsc::Receiver<Vec<u8>>),
+    /// Bytes read from an async reader.
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+    /// Server-sent events, with a keep-alive comment whenever none have been sent for the given interval.
+    Events(mpsc::Receiver<Event>, Option<Duration>),
+}
+
+impl Source {
+    /// Get the next chunk of the body, or `None` once it's over.
+    async fn next(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Channel(receiver) => Ok(receiver.recv().await),
+            Self::Reader(reader) => {
+                let mut chunk = Vec::with_capacity(READ_SIZE);
+
+                Ok((reader.read_buf(&mut chunk).await? > 0).then_some(chunk))
+            }
+            Self::Events(receiver, None) => Ok(receiver.recv().await.map(|event| event.to_bytes())),
+            Self::Events(receiver, Some(interval)) => tokio::select! {
+                event = receiver.recv() => Ok(event.map(|event| event.to_bytes())),
+                () = sleep(*interval) => Ok(Some(b": keep-alive\n\n".to_vec())),
+            },
+        }
+    }
+}
+
+/// A body that is sent in chunks as it is produced, instead of being held in memory.
+///
+/// Streams are sent with `Transfer-Encoding: chunked`, unless their length is known
+/// (see [`BodyStream::with_length`]), in which case they're sent as-is with a `Content-Length` header.
+/// Chunks are only read as fast as the client receives them, so producers are slowed down
+/// instead of buffering the whole body.
+///
+/// A stream can only be sent once: clones share the same underlying source.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{BodyStream, Response, Server};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.get("/numbers.csv", || async {
+///     let (sender, body) = BodyStream::channel(16);
+///
+///     tokio::spawn(async move {
+///         for i in 0..1_000_000 {
+///             if sender.send(format!("{i}\n").into_bytes()).await.is_err() {
+///                 break;
+///             }
+///         }
+///     });
+///
+///     Response::stream(body).header("Content-Type", "text/csv")
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BodyStream {
+    /// The source of the chunks, taken when the body is sent.
+    source: Arc<Mutex<Option<Source>>>,
+    /// The total length of the body, if known.
+    length: Option<u64>,
+}
+
+impl BodyStream {
+    /// Create a stream fed by a channel, which ends when the sender is dropped.
+    /// `capacity` is the number of chunks that can be buffered before sending waits for the client.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `capacity` is zero.
+    #[must_use]
+    pub fn channel(capacity: usize) -> (mpsc::Sender<Vec<u8>>, Self) {
+        let (sender, receiver) = mpsc::channel(capacity);
+
+        (sender, Self::new(Source::Channel(receiver)))
+    }
+
+    /// Create a stream that reads from `reader` until it reaches EOF.
+    #[must_use]
+    pub fn from_reader(reader: impl AsyncRead + Send + 'static) -> Self {
+        Self::new(Source::Reader(Box::pin(reader)))
+    }
+
+    /// Create a stream of server-sent events, sending a comment whenever none have been sent for `keep_alive`.
+    pub(crate) fn events(receiver: mpsc::Receiver<Event>, keep_alive: Option<Duration>) -> Self {
+        Self::new(Source::Events(receiver, keep_alive))
+    }
+
+    /// Set the total length of the stream, so it can be sent with a `Content-Length` header.
+    ///
+    /// The connection will be aborted if the stream ends up being shorter or longer than this.
+    #[must_use]
+    pub const fn with_length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+
+        self
+    }
+
+    /// The total length of the stream, if known.
+    #[must_use]
+    pub const fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    /// Create a stream of unknown length from a source.
+    fn new(source: Source) -> Self {
+        Self {
+            source: Arc::new(Mutex::new(Some(source))),
+            length: None,
+        }
+    }
+
+    /// Write the stream to `writer`, using the chunked transfer coding if `chunked` is set and its length isn't known.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the stream fails or doesn't match its length, or if writing fails.
+    /// In that case, the response is left incomplete so the client can tell it was cut short.
+    pub(crate) async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        chunked: bool,
+    ) -> io::Result<()> {
+        let chunked = chunked && self.length.is_none();
+        let mut chunks = self.chunks()?;
+
+        while let Some(chunk) = chunks.next().await? {
+            if !chunked {
+                writer.write_all(&chunk).await?;
+            } else if !chunk.is_empty() {
+                let mut frame = format!("{:X}\r\n", chunk.len()).into_bytes();
+                frame.extend_from_slice(&chunk);
+                frame.extend_from_slice(b"\r\n");
+
+                writer.write_all(&frame).await?;
+            }
+        }
+
+        if chunked {
+            writer.write_all(b"0\r\n\r\n").await?;
+        }
+
+        Ok(())
+    }
+
+    /// Start reading the chunks of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the stream has already been sent.
+    pub(crate) fn chunks(&self) -> io::Result<Chunks> {
+        let source = self
+            .source
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take();
+
+        let Some(source) = source else {
+            return Err(io::Error::other("body stream has already been sent"));
+        };
+
+        Ok(Chunks {
+            source,
+            length: self.length,
+            read: 0,
+        })
+    }
+}
+
+/// The chunks of a [`BodyStream`] being sent, checked against its length.
+pub struct Chunks {
+    /// Where the chunks come from.
+    source: Source,
+    /// The total length of the stream, if known.
+    length: Option<u64>,
+    /// The number of bytes read so far.
+    read: u64,
+}
+
+impl Chunks {
+    /// Get the next chunk of the body, or `None` once it's over.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the stream fails, or turns out to be shorter or longer than its length.
+    pub async fn next(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let chunk = self.source.next().await?;
+        let Some(length) = self.length else {
+            return Ok(chunk);
+        };
+
+        match &chunk {
+            Some(chunk) => {
+                self.read += chunk.len() as u64;
+                if self.read > length {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "body stream is longer than its length",
+                    ));
+                }
+            }
+            None if self.read < length => {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "body stream is shorter than its length",
+                ));
+            }
+            None => {}
+        }
+
+        Ok(chunk)
+    }
+}
+
+impl Debug for BodyStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyStream")
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Streams are only equal to their clones.
+impl PartialEq for BodyStream {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.source, &other.source)
+    }
+}
+
+impl Eq for BodyStream {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_writes_chunked_stream() {
+        let (sender, body) = BodyStream::channel(1);
+
+        tokio::spawn(async move {
+            for chunk in ["hello", "", ", world"] {
+                sender.send(chunk.as_bytes().to_vec()).await.unwrap();
+            }
+        });
+
+        let mut output = Vec::new();
+        body.write_to(&mut output, true).await.unwrap();
+
+        assert_eq!(output, b"5\r\nhello\r\n7\r\n, world\r\n0\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_writes_stream_with_length() {
+        let body = BodyStream::from_reader(&b"hello, world"[..]).with_length(12);
+
+        let mut output = Vec::new();
+        body.write_to(&mut output, true).await.unwrap();
+        assert_eq!(output, b"hello, world");
+
+        let short = BodyStream::from_reader(&b"hello"[..]).with_length(12);
+        let error = short.write_to(&mut Vec::new(), true).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let long = BodyStream::from_reader(&b"hello, world"[..]).with_length(5);
+        let error = long.write_to(&mut Vec::new(), true).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_only_sent_once() {
+        let body = BodyStream::from_reader(&b"hello"[..]);
+        let clone = body.clone();
+
+        assert_eq!(body, clone);
+        assert_ne!(body, BodyStream::from_reader(&b"hello"[..]));
+
+        body.write_to(&mut Vec::new(), true).await.unwrap();
+        assert!(clone.write_to(&mut Vec::new(), true).await.is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    fmt::{Debug, Formatter},
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::watch,
+};
+
+use super::parser::signalled;
+use crate::handler::BoxFuture;
+
+/// A connection that has switched to another protocol, after a 101 Switching Protocols response.
+/// See [`Response::on_upgrade`](crate::Response::on_upgrade) for more information.
+///
+/// This is the underlying TCP, Unix socket or TLS connection, starting with any bytes the client
+/// sent right after its request. Dropping it closes the connection.
+pub struct Upgraded {
+    /// The underlying connection, including any bytes the client sent after the request.
+    io: Pin<Box<dyn Io>>,
+    /// Set once the server starts shutting down.
+    shutdown: Option<watch::Receiver<bool>>,
+}
+
+/// A connection that can be read from and written to.
+trait Io: AsyncRead + AsyncWrite + Send + Sync {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Sync> Io for T {}
+
+impl Upgraded {
+    /// Wrap a connection, erasing its type.
+    pub(crate) fn new(io: impl AsyncRead + AsyncWrite + Send + Sync + 'static) -> Self {
+        Self {
+            io: Box::pin(io),
+            shutdown: None,
+        }
+    }
+
+    /// Let the new protocol know once `shutdown` is set.
+    pub(crate) fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
+    /// Wait until the server starts shutting down, so the connection can be wound down gracefully
+    /// before it's dropped (see [`Server::run_with_shutdown`](crate::Server::run_with_shutdown)).
+    /// Never resolves if the server isn't shutting down.
+    pub fn shutting_down(&self) -> impl Future<Output = ()> + Send + 'static {
+        let shutdown = self.shutdown.clone();
+
+        async move {
+            match shutdown {
+                Some(mut shutdown) => signalled(&mut shutdown).await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+}
+
+impl Debug for Upgraded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Upgraded").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.io.as_mut().poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.io.as_mut().poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.as_mut().poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.io.as_mut().poll_shutdown(cx)
+    }
+}
+
+/// A type-erased callback taking over an upgraded connection.
+type Callback = Box<dyn FnOnce(Upgraded) -> BoxFuture<()> + Send>;
+
+/// What to do with the connection once a response switching protocols has been sent.
+///
+/// Responses are cloned while being built, so clones share the same callback, which only runs once.
+#[derive(Clone)]
+pub struct OnUpgrade(Arc<Mutex<Option<Callback>>>);
+
+impl OnUpgrade {
+    /// Run `callback` with the connection once the response has been sent.
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: Callback = Box::new(move |io| Box::pin(callback(io)));
+
+        Self(Arc::new(Mutex::new(Some(callback))))
+    }
+
+    /// Hand the connection over to the callback, if it hasn't run yet.
+    pub async fn run(&self, io: Upgraded) {
+        let callback = self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+        if let Some(callback) = callback {
+            callback(io).await;
+        }
+    }
+}
+
+impl Debug for OnUpgrade {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnUpgrade").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,522 @@
+use crate::{
+    config::Config,
+    error::Error,
+    http::{
+        parse_query, signalled, Body, BodyStream, IntoResponse, Method, Request, Response, Version,
+    },
+    listener::ConnectionInfo,
+    router::Router,
+    state::StateMap,
+    StatusCode,
+};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use h2::{
+    server::{self, SendResponse},
+    Reason, RecvStream, SendStream,
+};
+use http::HeaderMap;
+use std::{collections::HashMap, future::poll_fn, sync::Arc};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::watch,
+    task::JoinSet,
+    time::sleep,
+};
+
+/// The maximum number of requests a client can have in flight on a single connection.
+const MAX_CONCURRENT_STREAMS: u32 = 100;
+
+/// Headers that only apply to HTTP/1 connections, and are forbidden in HTTP/2 (RFC 9113, section 8.2.2).
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Serve requests from an HTTP/2 connection, until the client closes it or the server shuts down.
+///
+/// Requests are handled concurrently, each on its own task. The connection is closed gracefully once
+/// it has been idle for [`Config::idle_timeout`], or has served [`Config::max_requests_per_connection`] requests.
+///
+/// # Errors
+///
+/// Will return an error if the connection fails, or the client violates the protocol.
+pub async fn serve<S>(
+    stream: S,
+    info: Arc<ConnectionInfo>,
+    router: Arc<Router>,
+    config: Arc<Config>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut connection = server::Builder::new()
+        .max_concurrent_streams(MAX_CONCURRENT_STREAMS)
+        .max_header_list_size(u32::try_from(config.max_head_size).unwrap_or(u32::MAX))
+        .handshake::<_, Bytes>(stream)
+        .await?;
+
+    let mut streams = JoinSet::new();
+    let mut served = 0;
+    let mut closing = false;
+
+    loop {
+        let idle = streams.is_empty();
+
+        tokio::select! {
+            accepted = connection.accept() => {
+                let Some(accepted) = accepted else {
+                    break;
+                };
+
+                let (request, respond) = accepted?;
+                let info = info.clone();
+                let router = router.clone();
+                let stream_config = config.clone();
+
+                streams.spawn(async move {
+                    if let Err(e) = handle(request, respond, info, router, &stream_config).await {
+                        println!("error: {e}");
+                    }
+                });
+
+                served += 1;
+                if served >= config.max_requests_per_connection && !closing {
+                    connection.graceful_shutdown();
+                    closing = true;
+                }
+            },
+            // Reap finished requests, so they don't pile up.
+            Some(_) = streams.join_next(), if !streams.is_empty() => {},
+            () = signalled(&mut shutdown), if !closing => {
+                connection.graceful_shutdown();
+                closing = true;
+            },
+            () = sleep(config.idle_timeout), if idle && !closing => {
+                connection.graceful_shutdown();
+                closing = true;
+            },
+        }
+    }
+
+    while streams.join_next().await.is_some() {}
+
+    Ok(())
+}
+
+/// Handle a single request (stream) of an HTTP/2 connection.
+async fn handle(
+    request: http::Request<RecvStream>,
+    respond: SendResponse<Bytes>,
+    info: Arc<ConnectionInfo>,
+    router: Arc<Router>,
+    config: &Config,
+) -> Result<()> {
+    let (response, head) = match read_request(request, info, config).await {
+        Ok(request) => {
+            let head = request.method == Method::Head;
+
+            (router.handle(request).await, head)
+        }
+        Err(error) => {
+            println!("error: invalid request: {error}");
+
+            (error.into_response(), false)
+        }
+    };
+
+    let mut response = response.check_upgrade(Version::Http2);
+    response.add_default_headers(config.server_header.as_deref());
+
+    send_response(respond, &response, head).await
+}
+
+/// Read the headers and body of a request, applying the configured limits.
+async fn read_request(
+    request: http::Request<RecvStream>,
+    info: Arc<ConnectionInfo>,
+    config: &Config,
+) -> Result<Request, Error> {
+    let (parts, mut body) = request.into_parts();
+
+    let method = parts
+        .method
+        .as_str()
+        .parse::<Method>()
+        .map_err(|e| Error::from(e).with_status(StatusCode::NotImplemented))?;
+
+    let mut headers = into_map(&parts.headers)?;
+    if headers.len() > config.max_headers {
+        return Err(Error::new(
+            StatusCode::RequestHeaderFieldsTooLarge,
+            "Too many headers",
+        ));
+    }
+
+    // HTTP/2 moves the `Host` header into the `:authority` pseudo-header (RFC 9113, section 8.3.1).
+    if let Some(authority) = parts.uri.authority() {
+        headers
+            .entry("host".to_string())
+            .or_insert_with(|| authority.to_string());
+    }
+
+    let mut request = Request {
+        method,
+        path: parts.uri.path().to_string(),
+        version: Version::Http2,
+        query: parse_query(parts.uri.query().unwrap_or_default()),
+        params: HashMap::new(),
+        headers,
+        body: Body::None,
+        trailers: HashMap::new(),
+        state: StateMap::default(),
+        connection: info,
+    };
+
+    let too_large = || Error::new(StatusCode::ContentTooLarge, "Request body too large");
+    let content_length = request
+        .content_length()
+        .map_err(|e| Error::from(e).with_status(StatusCode::BadRequest))?;
+    if content_length.unwrap_or_default() > config.max_body_size {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| Error::from(e).with_status(StatusCode::BadRequest))?;
+        _ = body.flow_control().release_capacity(chunk.len());
+
+        if bytes.len() + chunk.len() > config.max_body_size {
+            return Err(too_large());
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if let Some(trailers) = body
+        .trailers()
+        .await
+        .map_err(|e| Error::from(e).with_status(StatusCode::BadRequest))?
+    {
+        request.trailers = into_map(&trailers)?;
+    }
+
+    if !bytes.is_empty() {
+        request.body = request
+            .parse_body(bytes)
+            .map_err(|e| Error::from(e).with_status(StatusCode::BadRequest))?;
+    }
+
+    Ok(request)
+}
+
+/// Collect headers into a map, joining repeated ones like HTTP/1 clients would have sent them.
+fn into_map(headers: &HeaderMap) -> Result<HashMap<String, String>, Error> {
+    let mut map = HashMap::<String, String>::new();
+
+    for (name, value) in headers {
+        let value = value.to_str().map_err(|_| {
+            Error::new(
+                StatusCode::BadRequest,
+                "Header values must be visible ASCII",
+            )
+        })?;
+
+        // Clients may split cookies into several headers, which must be joined with semicolons (RFC 9113, section 8.2.3).
+        let separator = if name == http::header::COOKIE {
+            "; "
+        } else {
+            ", "
+        };
+
+        map.entry(name.as_str().to_string())
+            .and_modify(|existing| {
+                existing.push_str(separator);
+                existing.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+
+    Ok(map)
+}
+
+/// Send a response, streaming its body if needed.
+/// Responses to HEAD requests are sent without a body.
+async fn send_response(
+    mut respond: SendResponse<Bytes>,
+    response: &Response,
+    head: bool,
+) -> Result<()> {
+    let stream = match &response.body {
+        Body::Stream(stream) => Some(stream),
+        _ => None,
+    };
+    let bytes = Bytes::from(response.body.to_bytes());
+
+    let mut builder = http::Response::builder().status(response.status_code.as_u16());
+    for (name, value) in &response.headers {
+        if !CONNECTION_HEADERS.contains(&name.to_lowercase().as_str()) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+
+    let length = stream.map_or(Some(bytes.len() as u64), BodyStream::length);
+    if let Some(length) = length
+        .filter(|_| response.allows_body() && response.header_value("Content-Length").is_none())
+    {
+        builder = builder.header("content-length", length);
+    }
+
+    let head_only = head || !response.allows_body() || (stream.is_none() && bytes.is_empty());
+    let parts = match builder.body(()) {
+        Ok(parts) => parts,
+        Err(e) => {
+            respond.send_reset(Reason::INTERNAL_ERROR);
+
+            return Err(anyhow!("invalid response: {e}"));
+        }
+    };
+
+    let mut send = respond.send_response(parts, head_only)?;
+    if head_only {
+        return Ok(());
+    }
+
+    let Some(stream) = stream else {
+        return send_data(&mut send, bytes, true).await;
+    };
+
+    let mut chunks = stream.chunks()?;
+    loop {
+        match chunks.next().await {
+            Ok(Some(chunk)) => send_data(&mut send, chunk.into(), false).await?,
+            Ok(None) => return send_data(&mut send, Bytes::new(), true).await,
+            Err(e) => {
+                // Let the client know the response was cut short.
+                send.send_reset(Reason::INTERNAL_ERROR);
+
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Send data on a stream, waiting for the client to be ready to receive it.
+async fn send_data(
+    send: &mut SendStream<Bytes>,
+    mut data: Bytes,
+    end_of_stream: bool,
+) -> Result<()> {
+    if data.is_empty() {
+        if end_of_stream {
+            send.send_data(data, true)?;
+        }
+
+        return Ok(());
+    }
+
+    while !data.is_empty() {
+        send.reserve_capacity(data.len());
+
+        let capacity = poll_fn(|cx| send.poll_capacity(cx))
+            .await
+            .ok_or_else(|| anyhow!("stream closed before the response was sent"))??;
+
+        let chunk = data.split_to(capacity.min(data.len()));
+        send.send_data(chunk, end_of_stream && data.is_empty())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+    use h2::client::SendRequest;
+    use std::{net::SocketAddr, time::Duration};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        time::timeout,
+    };
+
+    async fn serve(config: Config) -> SocketAddr {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.config = config;
+
+        http.routes
+            .get("/", |request: Request| {
+                format!("{} {}", request.version, request.headers["host"])
+            })
+            .get("/slow", || async {
+                sleep(Duration::from_secs(1)).await;
+
+                "slow"
+            })
+            .post("/echo", |request: Request| {
+                format!("{} {}", request.query["name"], request.body)
+            })
+            .get("/stream", || {
+                let (sender, body) = BodyStream::channel(1);
+
+                tokio::spawn(async move {
+                    for chunk in ["a", "b", "c"] {
+                        sender.send(chunk.as_bytes().to_vec()).await.unwrap();
+                    }
+                });
+
+                Response::stream(body)
+            });
+
+        tokio::spawn(async move {
+            http.run().await;
+        });
+
+        addr
+    }
+
+    /// Open an HTTP/2 connection with prior knowledge.
+    async fn connect(addr: SocketAddr) -> SendRequest<Bytes> {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (client, connection) = h2::client::handshake(stream).await.unwrap();
+
+        tokio::spawn(async move {
+            _ = connection.await;
+        });
+
+        client
+    }
+
+    /// Send a request, returning the head of the response and its body.
+    async fn send(
+        client: &SendRequest<Bytes>,
+        request: http::Request<()>,
+        body: Option<&str>,
+    ) -> (http::response::Parts, String) {
+        let mut client = client.clone().ready().await.unwrap();
+        let (response, mut stream) = client.send_request(request, body.is_none()).unwrap();
+        if let Some(body) = body {
+            stream
+                .send_data(Bytes::from(body.to_string()), true)
+                .unwrap();
+        }
+
+        let (parts, mut body) = response.await.unwrap().into_parts();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.unwrap();
+            _ = body.flow_control().release_capacity(chunk.len());
+
+            bytes.extend_from_slice(&chunk);
+        }
+
+        (parts, String::from_utf8(bytes).unwrap())
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> http::Request<()> {
+        http::Request::get(format!("http://{addr}{path}"))
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serves_prior_knowledge_connections() {
+        let addr = serve(Config::default()).await;
+        let client = connect(addr).await;
+
+        let (parts, body) = send(&client, get(addr, "/"), None).await;
+
+        assert_eq!(parts.status, 200);
+        assert_eq!(parts.version, http::Version::HTTP_2);
+        assert_eq!(body, format!("HTTP/2 {addr}"));
+        assert_eq!(parts.headers["content-length"], body.len().to_string());
+        assert!(parts.headers.contains_key("date"));
+        assert!(!parts.headers.contains_key("connection"));
+
+        let (parts, _) = send(&client, get(addr, "/missing"), None).await;
+        assert_eq!(parts.status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_multiplexes_requests() {
+        let addr = serve(Config::default()).await;
+        let client = connect(addr).await;
+
+        let slow = tokio::spawn({
+            let client = client.clone();
+
+            async move { send(&client, get(addr, "/slow"), None).await }
+        });
+
+        // The slow request is still in flight on the same connection, but doesn't hold this one back.
+        let (_, body) = timeout(
+            Duration::from_millis(500),
+            send(&client, get(addr, "/"), None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(body, format!("HTTP/2 {addr}"));
+
+        let (_, body) = slow.await.unwrap();
+        assert_eq!(body, "slow");
+    }
+
+    #[tokio::test]
+    async fn test_reads_request_bodies() {
+        let addr = serve(Config {
+            max_body_size: 16,
+            ..Config::default()
+        })
+        .await;
+        let client = connect(addr).await;
+        let post = || {
+            http::Request::post(format!("http://{addr}/echo?name=Miguel"))
+                .header("content-type", "text/plain")
+                .body(())
+                .unwrap()
+        };
+
+        let (parts, body) = send(&client, post(), Some("Hello!")).await;
+        assert_eq!(parts.status, 200);
+        assert_eq!(body, "Miguel Hello!");
+
+        let (parts, _) = send(&client, post(), Some(&"a".repeat(17))).await;
+        assert_eq!(parts.status, 413);
+    }
+
+    #[tokio::test]
+    async fn test_streams_responses() {
+        let addr = serve(Config::default()).await;
+        let client = connect(addr).await;
+
+        let (parts, body) = send(&client, get(addr, "/stream"), None).await;
+
+        assert_eq!(parts.status, 200);
+        assert!(!parts.headers.contains_key("transfer-encoding"));
+        assert_eq!(body, "abc");
+    }
+
+    #[tokio::test]
+    async fn test_still_serves_http_1() {
+        let addr = serve(Config::default()).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.ends_with("HTTP/1.1 example.com"), "{response}");
+    }
+}
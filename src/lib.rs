@@ -1,4 +1,5 @@
 #![feature(rustdoc_missing_doc_code_examples)]
+#![cfg_attr(test, feature(test))]
 #![warn(
     clippy::all,
     clippy::pedantic,
@@ -13,14 +14,14 @@
 //! ## Example
 //!
 //! ```rust,no_run
-//! use lil_http::{Server, Response};
+//! use lil_http::{Request, Response, Server};
 //!
 //! #[tokio::main]
 //! async fn main() {
 //!    let mut http = Server::new().await.unwrap();
 //!
 //!    http.routes
-//!        .get("/", |request| {
+//!        .get("/", |request: Request| {
 //!            println!("Received {} request to {}", request.method, request.path);
 //!
 //!            Response::text("Hello, World!")
@@ -30,9 +31,38 @@
 //! }
 //! ```
 
+mod config;
+mod error;
+mod extract;
+mod handler;
 mod http;
+#[cfg(feature = "http2")]
+mod http2;
+mod listener;
+mod middleware;
 mod router;
 mod server;
+mod state;
+#[cfg(feature = "tls")]
+mod tls;
+mod websocket;
 
-pub use http::{Body, Method, Request, Response, StatusCode};
+pub use config::Config;
+pub use error::Error;
+pub use extract::{FromRequest, Header, Json, Path, Query};
+pub use handler::{Handler, HandlerOutput};
+pub use http::{
+    Body, BodyStream, CustomStatus, Event, IntoResponse, Method, Request, Response, Sse,
+    StatusCode, Upgraded, Version,
+};
+#[cfg(unix)]
+pub use listener::UnixOptions;
+pub use listener::{ConnectionInfo, PeerCredentials};
+pub use middleware::{Middleware, Next};
 pub use server::Server;
+pub use state::State;
+#[cfg(feature = "tls")]
+pub use tls::{ClientAuth, PeerCertificate, Tls, TlsInfo};
+pub use websocket::{
+    CloseFrame, Message, WebSocket, WebSocketConfig, WebSocketReceiver, WebSocketSender,
+};
@@ -0,0 +1,295 @@
+use std::{
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpListener, TcpStream},
+};
+
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+/// Information about the connection a request was received on.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Request, Server};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.get("/whoami", |request: Request| {
+///     match (request.connection().remote_addr, &request.connection().peer_credentials) {
+///         (Some(addr), _) => format!("{addr}"),
+///         (_, Some(credentials)) => format!("uid {}", credentials.uid),
+///         _ => "unknown".to_string(),
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ConnectionInfo {
+    /// The address of the client, for TCP connections.
+    pub remote_addr: Option<SocketAddr>,
+    /// The credentials of the process on the other end, for Unix socket connections.
+    pub peer_credentials: Option<PeerCredentials>,
+    /// The TLS session, for servers with TLS enabled.
+    #[cfg(feature = "tls")]
+    pub tls: Option<crate::TlsInfo>,
+}
+
+/// The credentials of the process on the other end of a Unix socket.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PeerCredentials {
+    /// The user ID of the process.
+    pub uid: u32,
+    /// The group ID of the process.
+    pub gid: u32,
+    /// The ID of the process, on platforms that report it.
+    pub pid: Option<i32>,
+}
+
+/// Options for serving on a Unix socket.
+/// See [`Server::bind_unix`](crate::Server::bind_unix) for more information.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnixOptions {
+    /// The permissions to set on the socket file (e.g. `0o660`), if any.
+    pub permissions: Option<u32>,
+    /// Whether to remove a stale socket left at the path (e.g. by a crashed process) before binding.
+    ///
+    /// A socket a running server still accepts connections on is never removed.
+    pub remove_existing: bool,
+    /// Whether to remove the socket file once the server is dropped.
+    pub remove_on_drop: bool,
+}
+
+#[cfg(unix)]
+impl Default for UnixOptions {
+    fn default() -> Self {
+        Self {
+            permissions: None,
+            remove_existing: true,
+            remove_on_drop: true,
+        }
+    }
+}
+
+/// A Unix socket listener, which can clean up its socket file when dropped.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixSocket {
+    /// The underlying listener.
+    listener: UnixListener,
+    /// The path of the socket file.
+    path: PathBuf,
+    /// Whether to remove the socket file when dropped.
+    remove_on_drop: bool,
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        if self.remove_on_drop {
+            _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// A listener accepting connections for the server.
+#[derive(Debug)]
+pub enum Listener {
+    /// A TCP listener.
+    Tcp(TcpListener),
+    /// A Unix socket listener.
+    #[cfg(unix)]
+    Unix(UnixSocket),
+}
+
+impl Listener {
+    /// Bind a Unix socket at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the path is already in use (including by a running server),
+    /// or the socket can't be bound, or its permissions can't be set.
+    #[cfg(unix)]
+    pub fn bind_unix(path: &Path, options: UnixOptions) -> io::Result<Self> {
+        use std::{fs, os::unix::fs::FileTypeExt};
+
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            // Only remove sockets nobody is listening on anymore, so a typo in the path can't delete
+            // an unrelated file, and a second instance can't take over the socket of a running one.
+            let stale = options.remove_existing
+                && metadata.file_type().is_socket()
+                && matches!(
+                    std::os::unix::net::UnixStream::connect(path),
+                    Err(error) if error.kind() == io::ErrorKind::ConnectionRefused
+                );
+            if !stale {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is already in use", path.display()),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+
+        let listener = match options.permissions {
+            Some(mode) => bind_with_permissions(path, mode)?,
+            None => UnixListener::bind(path)?,
+        };
+
+        Ok(Self::Unix(UnixSocket {
+            listener,
+            path: path.to_path_buf(),
+            remove_on_drop: options.remove_on_drop,
+        }))
+    }
+
+    /// Accept a new connection, along with information about it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if accepting the connection fails.
+    pub async fn accept(&self) -> io::Result<(Stream, ConnectionInfo)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+
+                Ok((
+                    Stream::Tcp(stream),
+                    ConnectionInfo {
+                        remote_addr: Some(addr),
+                        ..ConnectionInfo::default()
+                    },
+                ))
+            }
+            #[cfg(unix)]
+            Self::Unix(socket) => {
+                let (stream, _) = socket.listener.accept().await?;
+                let peer_credentials = stream.peer_cred().ok().map(|credentials| PeerCredentials {
+                    uid: credentials.uid(),
+                    gid: credentials.gid(),
+                    pid: credentials.pid(),
+                });
+
+                Ok((
+                    Stream::Unix(stream),
+                    ConnectionInfo {
+                        peer_credentials,
+                        ..ConnectionInfo::default()
+                    },
+                ))
+            }
+        }
+    }
+
+    /// The address the listener is bound to, for TCP listeners.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr(),
+            #[cfg(unix)]
+            Self::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "not listening on a TCP socket",
+            )),
+        }
+    }
+
+    /// A description of where the listener is bound, for logging.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Tcp(listener) => listener
+                .local_addr()
+                .map_or_else(|_| "TCP socket".to_string(), |addr| addr.to_string()),
+            #[cfg(unix)]
+            Self::Unix(socket) => socket.path.display().to_string(),
+        }
+    }
+}
+
+/// A connection accepted by a [`Listener`].
+#[derive(Debug)]
+pub enum Stream {
+    /// A TCP connection.
+    Tcp(TcpStream),
+    /// A Unix socket connection.
+    #[cfg(unix)]
+    Unix(UnixStream),
+    /// A TLS connection, over any of the other kinds.
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::server::TlsStream<Self>>),
+}
+
+macro_rules! delegate {
+    ($self:ident, $stream:ident => $call:expr) => {
+        match $self.get_mut() {
+            Self::Tcp($stream) => $call,
+            #[cfg(unix)]
+            Self::Unix($stream) => $call,
+            #[cfg(feature = "tls")]
+            Self::Tls($stream) => $call,
+        }
+    };
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        delegate!(self, stream => Pin::new(stream).poll_read(cx, buf))
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        delegate!(self, stream => Pin::new(stream).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        delegate!(self, stream => Pin::new(stream).poll_flush(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        delegate!(self, stream => Pin::new(stream).poll_shutdown(cx))
+    }
+}
+
+/// Bind a Unix socket at `path` with the given permissions.
+///
+/// The socket is bound inside a directory only we can access and moved into place once its
+/// permissions are set, so nobody can connect to it while it still has the default ones.
+#[cfg(unix)]
+fn bind_with_permissions(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    use std::{
+        fs,
+        os::unix::fs::{DirBuilderExt, PermissionsExt},
+    };
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let directory = path.with_file_name(format!(".{name}.{}", std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&directory)?;
+
+    let socket = directory.join("socket");
+    let listener = UnixListener::bind(&socket).and_then(|listener| {
+        fs::set_permissions(&socket, fs::Permissions::from_mode(mode))?;
+        fs::rename(&socket, path)?;
+        Ok(listener)
+    });
+
+    _ = fs::remove_dir_all(&directory);
+    listener
+}
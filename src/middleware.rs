@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use crate::{
+    handler::{BoxFuture, BoxedHandler, HandlerOutput},
+    http::{Request, Response},
+};
+
+/// A type-erased middleware.
+pub type BoxedMiddleware = Arc<dyn Fn(Request, Next) -> BoxFuture<Response> + Send + Sync>;
+
+/// Code that runs around route handlers.
+///
+/// A middleware receives the incoming [`Request`] and a [`Next`] continuation. It can
+/// inspect or modify the request before passing it on with [`Next::run`], transform the
+/// response it gets back, or short-circuit the chain by returning a response of its own.
+///
+/// Closures taking a request and a [`Next`] (and returning a [`HandlerOutput`]) implement this
+/// trait, so most middleware can be written inline. The `M` parameter only exists to tell
+/// the closure implementations apart, and can be left out when implementing this trait.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Next, Request, Server};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.middleware(|request: Request, next: Next| async move {
+///     println!("Received {} request to {}", request.method, request.path);
+///
+///     next.run(request).await.header("X-Powered-By", "lil-http")
+/// });
+/// # }
+/// ```
+pub trait Middleware<M = ()>: Send + Sync + 'static {
+    /// Handle a request, optionally calling the rest of the chain through `next`.
+    fn handle(&self, request: Request, next: Next) -> BoxFuture<Response>;
+}
+
+impl<F, R, M> Middleware<M> for F
+where
+    F: Fn(Request, Next) -> R + Send + Sync + 'static,
+    R: HandlerOutput<M>,
+{
+    fn handle(&self, request: Request, next: Next) -> BoxFuture<Response> {
+        self(request, next).into_future()
+    }
+}
+
+/// The rest of the middleware chain, ending with the route handler.
+pub struct Next {
+    /// The middleware that still have to run, in order.
+    middleware: Arc<[BoxedMiddleware]>,
+    /// The index of the next middleware to run.
+    index: usize,
+    /// The handler to call once every middleware has run.
+    endpoint: BoxedHandler,
+}
+
+impl Next {
+    /// Create a chain running the given middleware in order, and then the endpoint.
+    pub(crate) fn new(middleware: Vec<BoxedMiddleware>, endpoint: BoxedHandler) -> Self {
+        Self {
+            middleware: middleware.into(),
+            index: 0,
+            endpoint,
+        }
+    }
+
+    /// Run the rest of the chain, returning the response.
+    pub async fn run(self, request: Request) -> Response {
+        match self.middleware.get(self.index).cloned() {
+            Some(middleware) => {
+                let next = Self {
+                    index: self.index + 1,
+                    ..self
+                };
+
+                middleware(request, next).await
+            }
+            None => (self.endpoint)(request).await,
+        }
+    }
+}
+
+/// Erase the type of a middleware, so it can be stored alongside others.
+pub fn boxed<M>(middleware: impl Middleware<M>) -> BoxedMiddleware {
+    Arc::new(move |request, next| middleware.handle(request, next))
+}
@@ -0,0 +1,643 @@
+use std::{future::Future, sync::Arc};
+
+use crate::{
+    error::Error,
+    handler::{self, BoxFuture, BoxedHandler, CatchUnwind, Handler},
+    http::{IntoResponse, Method, Request, Response},
+    middleware::{self, BoxedMiddleware, Middleware, Next},
+    state::{self, StateMap},
+    websocket::{self, WebSocket, WebSocketConfig},
+};
+use tree::Node;
+
+mod tree;
+
+/// The router is responsible for matching requests to handlers.
+#[derive(Clone)]
+pub struct Router {
+    routes: Node<BoxedHandler>,
+    /// Middleware that run around every request, in registration order.
+    middleware: Vec<BoxedMiddleware>,
+    /// The application state made available to every request.
+    state: StateMap,
+    /// Turns errors returned by handlers into responses, if customized.
+    error_handler: Option<ErrorHandler>,
+}
+
+/// A type-erased error handler.
+type ErrorHandler = Arc<dyn Fn(&Error) -> Response + Send + Sync>;
+
+impl Router {
+    /// Create a new router instance.
+    pub(crate) fn new() -> Self {
+        Self {
+            routes: Node::new(),
+            middleware: Vec::new(),
+            state: StateMap::default(),
+            error_handler: None,
+        }
+    }
+
+    /// Match a route to a handler.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a wildcard isn't the last segment of the path, or if the path
+    /// names a parameter differently than an already registered route at the same position.
+    pub(crate) fn r#match<Args>(
+        &mut self,
+        methods: Vec<Method>,
+        path: &str,
+        handler: impl Handler<Args>,
+    ) -> &mut Self {
+        let handler = handler::boxed(handler);
+
+        for method in methods {
+            self.routes.insert(path, method, handler.clone());
+        }
+
+        self
+    }
+
+    /// Register a GET route.
+    ///
+    /// Handlers can return anything implementing [`IntoResponse`](crate::IntoResponse),
+    /// or a future resolving to it.
+    /// See [`Handler`] for more information.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::{Path, Request, Response, Server};
+    ///
+    /// # async fn example() {
+    /// let mut http = Server::new().await.unwrap();
+    ///
+    /// http.routes
+    ///     .get("/", |request: Request| Response::text(&request.path))
+    ///     .get("/users/:id", |Path(id): Path<u32>| Response::text(&format!("User #{id}")))
+    ///     .get("/async", || async {
+    ///         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    ///
+    ///         Response::text("Hello, eventually!")
+    ///     });
+    /// # }
+    /// ```
+    pub fn get<Args>(&mut self, path: &str, handler: impl Handler<Args>) -> &mut Self {
+        self.r#match(vec![Method::Get], path, handler)
+    }
+
+    /// Register a POST route.
+    pub fn post<Args>(&mut self, path: &str, handler: impl Handler<Args>) -> &mut Self {
+        self.r#match(vec![Method::Post], path, handler)
+    }
+
+    /// Register a PUT route.
+    pub fn put<Args>(&mut self, path: &str, handler: impl Handler<Args>) -> &mut Self {
+        self.r#match(vec![Method::Put], path, handler)
+    }
+
+    /// Register a DELETE route.
+    pub fn delete<Args>(&mut self, path: &str, handler: impl Handler<Args>) -> &mut Self {
+        self.r#match(vec![Method::Delete], path, handler)
+    }
+
+    /// Register a WebSocket endpoint, handing every connection to `handler` once the handshake succeeds.
+    ///
+    /// Requests that aren't a valid WebSocket handshake get a 426 Upgrade Required (or 400 Bad Request) response.
+    /// The connection is closed once the handler returns, with code 1011 if it returned an error.
+    /// See [`WebSocket`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the path is not a valid route pattern (see [`Router::get`]).
+    pub fn websocket<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    where
+        F: Fn(Request, WebSocket) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.websocket_with_config(path, WebSocketConfig::default(), handler)
+    }
+
+    /// Register a WebSocket endpoint with custom limits.
+    /// See [`Router::websocket`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the path is not a valid route pattern (see [`Router::get`]).
+    pub fn websocket_with_config<F, Fut>(
+        &mut self,
+        path: &str,
+        config: WebSocketConfig,
+        handler: F,
+    ) -> &mut Self
+    where
+        F: Fn(Request, WebSocket) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+
+        self.get(path, move |request: Request| {
+            websocket::accept(request, config, handler.clone())
+        })
+    }
+
+    /// Register a middleware that runs around every request, including those that don't match a route.
+    ///
+    /// Global middleware run in the order they were registered, before any route middleware.
+    pub fn middleware<M>(&mut self, middleware: impl Middleware<M>) -> &mut Self {
+        self.middleware.push(middleware::boxed(middleware));
+
+        self
+    }
+
+    /// Register a middleware that runs around the handlers of the given route, for every method.
+    ///
+    /// Route middleware run in the order they were registered, after every global middleware.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the path is not a valid route pattern (see [`Router::get`]).
+    pub fn route_middleware<M>(&mut self, path: &str, middleware: impl Middleware<M>) -> &mut Self {
+        self.routes.layer(path, middleware::boxed(middleware));
+
+        self
+    }
+
+    /// Only let requests to the given route through if the client presented a verified certificate over TLS,
+    /// responding with 403 Forbidden otherwise. See [`Tls::with_client_auth`](crate::Tls::with_client_auth) for more information.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the path is not a valid route pattern (see [`Router::get`]).
+    #[cfg(feature = "tls")]
+    pub fn require_client_cert(&mut self, path: &str) -> &mut Self {
+        self.route_middleware(path, |request: Request, next: Next| async move {
+            if request.peer_certificate().is_none() {
+                return Error::new(
+                    crate::StatusCode::Forbidden,
+                    "a client certificate is required",
+                )
+                .into_response();
+            }
+
+            next.run(request).await
+        })
+    }
+
+    /// Make a value available to every handler, retrievable with [`Request::state`].
+    /// Registering a value replaces any previously registered value of the same type.
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        state::insert(&mut self.state, state);
+
+        self
+    }
+
+    /// Customize how errors returned by handlers are turned into responses.
+    ///
+    /// By default, errors are turned into an `application/problem+json` response.
+    /// See [`Error`] for more information.
+    pub fn error_handler<R: IntoResponse>(
+        &mut self,
+        handler: impl Fn(&Error) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.error_handler = Some(Arc::new(move |error| handler(error).into_response()));
+
+        self
+    }
+
+    /// Handle an incoming request.
+    /// If no route is found, a 404 response is returned.
+    /// If a route is found, but the method is not allowed, a 405 response is returned.
+    /// When several routes match, static segments take priority over dynamic ones.
+    /// If a handler (or middleware) panics, a 500 response is returned.
+    pub(crate) async fn handle(&self, mut request: Request) -> Response {
+        let mut middleware = self.middleware.clone();
+        request.state = self.state.clone();
+
+        let endpoint = match self.routes.find(&request.path) {
+            None => respond_with(Response::not_found()),
+            Some((route, params)) => {
+                request.params = params;
+
+                route.get(request.method).map_or_else(
+                    || respond_with(Response::method_not_allowed(&route.methods())),
+                    |handler| {
+                        middleware.extend_from_slice(route.middleware());
+
+                        self.error_handler.clone().map_or_else(
+                            || handler.clone(),
+                            |error_handler| map_errors(handler.clone(), error_handler),
+                        )
+                    },
+                )
+            }
+        };
+
+        let response = Box::pin(Next::new(middleware, endpoint).run(request));
+
+        match CatchUnwind(response).await {
+            Ok(response) => response,
+            Err(panic) => {
+                println!(
+                    "error: handler panicked: {}",
+                    handler::panic_message(&*panic)
+                );
+
+                Response::internal_server_error()
+            }
+        }
+    }
+}
+
+/// Wrap a handler so the errors it returns are turned into responses by the given error handler.
+fn map_errors(handler: BoxedHandler, error_handler: ErrorHandler) -> BoxedHandler {
+    Arc::new(move |request| -> BoxFuture<Response> {
+        let response = handler(request);
+        let error_handler = error_handler.clone();
+
+        Box::pin(async move {
+            let response = response.await;
+
+            match response.error {
+                Some(error) => {
+                    let mut response = error_handler(&error);
+                    response.error = Some(error);
+
+                    response
+                }
+                None => response,
+            }
+        })
+    })
+}
+
+/// A handler that always returns the given response.
+fn respond_with(response: Response) -> BoxedHandler {
+    Arc::new(move |_| -> BoxFuture<Response> { Box::pin(std::future::ready(response.clone())) })
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http::Version, Body, Path, Query, StatusCode};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn request(method: Method, path: &str) -> Request {
+        Request {
+            method,
+            path: path.to_string(),
+            version: Version::Http11,
+            query: HashMap::new(),
+            params: HashMap::new(),
+            headers: HashMap::new(),
+            body: Body::None,
+            trailers: HashMap::new(),
+            state: StateMap::default(),
+            connection: Arc::default(),
+        }
+    }
+
+    #[test]
+    fn test_router_default() {
+        let router = Router::default();
+
+        assert_eq!(router.routes.len(), 0);
+    }
+
+    #[test]
+    fn test_router_match() {
+        let mut router = Router::new();
+
+        router.r#match(vec![Method::Put], "/test/path", |_: Request| {
+            Response::text("")
+        });
+
+        assert_eq!(router.routes.len(), 1);
+        assert!(router
+            .routes
+            .find("/test/path")
+            .is_some_and(|(route, _)| route.get(Method::Put).is_some()));
+    }
+
+    #[test]
+    fn test_router_get() {
+        let mut router = Router::new();
+
+        router.get("/test/path", |_: Request| Response::text(""));
+
+        assert_eq!(router.routes.len(), 1);
+        assert!(router
+            .routes
+            .find("/test/path")
+            .is_some_and(|(route, _)| route.get(Method::Get).is_some()));
+    }
+
+    #[test]
+    fn test_router_post() {
+        let mut router = Router::new();
+
+        router.post("/test/path", |_: Request| Response::text(""));
+
+        assert_eq!(router.routes.len(), 1);
+        assert!(router
+            .routes
+            .find("/test/path")
+            .is_some_and(|(route, _)| route.get(Method::Post).is_some()));
+    }
+
+    #[test]
+    fn test_router_put() {
+        let mut router = Router::new();
+
+        router.put("/test/path", |_: Request| Response::text(""));
+
+        assert_eq!(router.routes.len(), 1);
+        assert!(router
+            .routes
+            .find("/test/path")
+            .is_some_and(|(route, _)| route.get(Method::Put).is_some()));
+    }
+
+    #[test]
+    fn test_router_delete() {
+        let mut router = Router::new();
+
+        router.delete("/test/path", |_: Request| Response::text(""));
+
+        assert_eq!(router.routes.len(), 1);
+        assert!(router
+            .routes
+            .find("/test/path")
+            .is_some_and(|(route, _)| route.get(Method::Delete).is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_router_handle() {
+        let mut router = Router::new();
+
+        router.get("/test/path", |_: Request| Response::text("test response"));
+
+        let response = router.handle(request(Method::Get, "/test/path")).await;
+
+        assert_eq!(response.status_code, StatusCode::Ok);
+        assert_eq!(response.body, Body::Text("test response".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_returns_404_if_no_routes_match() {
+        let router = Router::new();
+
+        let response = router.handle(request(Method::Get, "/")).await;
+
+        assert_eq!(response.status_code, StatusCode::NotFound);
+        assert_eq!(response.body, Body::Text("Not Found".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_returns_405_if_method_does_not_match() {
+        let mut router = Router::new();
+
+        router
+            .put("/test/path", |_: Request| Response::text("test response"))
+            .get("/test/path", |_: Request| Response::text("test response"));
+
+        let response = router.handle(request(Method::Post, "/test/path")).await;
+
+        assert_eq!(response.status_code, StatusCode::MethodNotAllowed);
+        assert_eq!(response.body, Body::Text("Method Not Allowed".to_string()));
+        assert_eq!(
+            response.headers.get("Allow").unwrap(),
+            &"GET, PUT".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_params() {
+        let mut router = Router::new();
+
+        router.get("/users/:id", |request: Request| {
+            Response::text(request.params.get("id").unwrap())
+        });
+
+        let response = router.handle(request(Method::Get, "/users/42")).await;
+
+        assert_eq!(response.status_code, StatusCode::Ok);
+        assert_eq!(response.body, Body::Text("42".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_static_routes_take_priority() {
+        let mut router = Router::new();
+
+        router
+            .get("/users/*rest", |_: Request| Response::text("wildcard"))
+            .get("/users/:id", |_: Request| Response::text("dynamic"))
+            .get("/users/me", |_: Request| Response::text("static"));
+
+        assert_eq!(
+            router.handle(request(Method::Get, "/users/me")).await.body,
+            Body::Text("static".to_string())
+        );
+        assert_eq!(
+            router.handle(request(Method::Get, "/users/42")).await.body,
+            Body::Text("dynamic".to_string())
+        );
+        assert_eq!(
+            router
+                .handle(request(Method::Get, "/users/42/posts"))
+                .await
+                .body,
+            Body::Text("wildcard".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_async() {
+        let mut router = Router::new();
+
+        router.get("/test/path", |request: Request| async move {
+            tokio::task::yield_now().await;
+
+            Response::text(&request.path)
+        });
+
+        let response = router.handle(request(Method::Get, "/test/path")).await;
+
+        assert_eq!(response.status_code, StatusCode::Ok);
+        assert_eq!(response.body, Body::Text("/test/path".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_middleware_run_in_order() {
+        let mut router = Router::new();
+
+        router
+            .get("/test/path", |_: Request| Response::text("handler"))
+            .route_middleware("/test/path", |request, next: Next| async move {
+                next.run(request).await.header("X-Order", "route")
+            })
+            .middleware(|request, next: Next| async move {
+                let mut response = next.run(request).await;
+                let order = response.headers.get("X-Order").cloned().unwrap_or_default();
+
+                response.header("X-Order", &format!("{order}, first"))
+            })
+            .middleware(|request, next: Next| async move {
+                let mut response = next.run(request).await;
+                let order = response.headers.get("X-Order").cloned().unwrap_or_default();
+
+                response.header("X-Order", &format!("{order}, second"))
+            });
+
+        let response = router.handle(request(Method::Get, "/test/path")).await;
+
+        assert_eq!(response.body, Body::Text("handler".to_string()));
+        assert_eq!(
+            response.headers.get("X-Order"),
+            Some(&"route, second, first".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_can_short_circuit() {
+        let mut router = Router::new();
+
+        router
+            .get("/test/path", |_: Request| Response::text("handler"))
+            .get("/admin", |_: Request| Response::text("secret"))
+            .route_middleware("/admin", |_, _| {
+                Response::text("Forbidden").status(StatusCode::BadRequest)
+            });
+
+        let response = router.handle(request(Method::Get, "/admin")).await;
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+        assert_eq!(response.body, Body::Text("Forbidden".to_string()));
+
+        let response = router.handle(request(Method::Get, "/test/path")).await;
+        assert_eq!(response.body, Body::Text("handler".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_global_middleware_run_on_404() {
+        let mut router = Router::new();
+
+        router.middleware(|request, next: Next| async move {
+            next.run(request).await.header("X-Middleware", "true")
+        });
+
+        let response = router.handle(request(Method::Get, "/missing")).await;
+
+        assert_eq!(response.status_code, StatusCode::NotFound);
+        assert_eq!(
+            response.headers.get("X-Middleware"),
+            Some(&"true".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_injects_state() {
+        let mut router = Router::new();
+
+        router
+            .with_state("shared".to_string())
+            .get("/test/path", |request: Request| {
+                let state = request.state::<String>().unwrap();
+
+                Response::text(&state)
+            });
+
+        let response = router.handle(request(Method::Get, "/test/path")).await;
+
+        assert_eq!(response.body, Body::Text("shared".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_extractors() {
+        let mut router = Router::new();
+
+        router.get(
+            "/users/:id",
+            |Path(id): Path<u32>, Query(query): Query<HashMap<String, String>>| {
+                Response::text(&format!("{id} {}", query["name"]))
+            },
+        );
+
+        let with_query = |path: &str| Request {
+            query: HashMap::from([("name".to_string(), "Miguel".to_string())]),
+            ..request(Method::Get, path)
+        };
+
+        let response = router.handle(with_query("/users/42")).await;
+        assert_eq!(response.body, Body::Text("42 Miguel".to_string()));
+
+        let response = router.handle(with_query("/users/me")).await;
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_into_response() {
+        let mut router = Router::new();
+
+        router
+            .get("/text", || "Hello, World!")
+            .get("/created", || async { (StatusCode::BadRequest, "Nope") })
+            .get("/missing", || None::<String>);
+
+        let response = router.handle(request(Method::Get, "/text")).await;
+        assert_eq!(response.body, Body::Text("Hello, World!".to_string()));
+
+        let response = router.handle(request(Method::Get, "/created")).await;
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+
+        let response = router.handle(request(Method::Get, "/missing")).await;
+        assert_eq!(response.status_code, StatusCode::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_router_error_handler() {
+        let mut router = Router::new();
+
+        router
+            .get("/fallible", || -> Result<&'static str, Error> {
+                Err(Error::new(StatusCode::BadRequest, "Invalid user"))
+            })
+            .error_handler(|error: &Error| {
+                (
+                    error.status().clone(),
+                    json!({ "error": error.to_string() }),
+                )
+            });
+
+        let response = router.handle(request(Method::Get, "/fallible")).await;
+
+        assert_eq!(response.status_code, StatusCode::BadRequest);
+        assert_eq!(
+            response.body,
+            Body::Json(json!({ "error": "Invalid user" }))
+        );
+        assert!(response.error().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_router_handle_panics() {
+        let mut router = Router::new();
+
+        router.get("/panic", || -> Response { panic!("oh no") });
+
+        let response = router.handle(request(Method::Get, "/panic")).await;
+
+        assert_eq!(response.status_code, StatusCode::InternalServerError);
+        assert_eq!(
+            response.body,
+            Body::Text("Internal Server Error".to_string())
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use crate::{http::Method, middleware::BoxedMiddleware};
+
+/// Split a path into its segments, ignoring the leading slash.
+fn segments(path: &str) -> Vec<&str> {
+    path.trim_start_matches('/').split('/').collect()
+}
+
+/// Whether a route segment captures a value (`:param` or `*wildcard`).
+fn is_dynamic(segment: &str) -> bool {
+    segment.starts_with(':') || segment.starts_with('*')
+}
+
+/// A node of the routing tree.
+///
+/// The tree is a radix tree keyed by path segments: chains of static segments
+/// are compressed into a single node, and each node holds the handlers
+/// registered for it in a per-method table. Looking up a path only walks the
+/// nodes along that path, so its cost doesn't depend on the number of routes.
+#[derive(Clone)]
+pub struct Node<T> {
+    /// The static segments leading to this node from its parent.
+    prefix: Vec<String>,
+    /// The static children of this node, keyed by the first segment of their prefix.
+    children: HashMap<String, Self>,
+    /// The `:param` child of this node, along with the name of the parameter.
+    param: Option<(String, Box<Self>)>,
+    /// The `*wildcard` child of this node, along with the name of the parameter.
+    wildcard: Option<(String, Box<Self>)>,
+    /// The values registered for this node, by method.
+    endpoints: HashMap<Method, T>,
+    /// The middleware that run around every value registered for this node.
+    middleware: Vec<BoxedMiddleware>,
+}
+
+impl<T> Node<T> {
+    /// Create an empty tree.
+    pub fn new() -> Self {
+        Self {
+            prefix: Vec::new(),
+            children: HashMap::new(),
+            param: None,
+            wildcard: None,
+            endpoints: HashMap::new(),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Register a value for the given path pattern and method.
+    /// If a value was already registered, it will be overwritten.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a wildcard isn't the last segment of the path, or if the path
+    /// names a parameter differently than an already registered route at the same position.
+    pub fn insert(&mut self, path: &str, method: Method, value: T) {
+        self.insert_segments(&segments(path))
+            .endpoints
+            .insert(method, value);
+    }
+
+    /// Register a middleware for the given path pattern, running around every method.
+    ///
+    /// # Panics
+    ///
+    /// Will panic under the same conditions as [`Node::insert`].
+    pub fn layer(&mut self, path: &str, middleware: BoxedMiddleware) {
+        self.insert_segments(&segments(path))
+            .middleware
+            .push(middleware);
+    }
+
+    /// Get the middleware registered for this node, in registration order.
+    pub fn middleware(&self) -> &[BoxedMiddleware] {
+        &self.middleware
+    }
+
+    /// Get the value registered for the given method on this node.
+    pub fn get(&self, method: Method) -> Option<&T> {
+        self.endpoints.get(&method)
+    }
+
+    /// Get the methods that have a value registered on this node.
+    pub fn methods(&self) -> Vec<Method> {
+        self.endpoints.keys().copied().collect()
+    }
+
+    /// The number of (path, method) pairs registered in the tree.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+            + self.children.values().map(Self::len).sum::<usize>()
+            + self.param.as_ref().map_or(0, |(_, node)| node.len())
+            + self.wildcard.as_ref().map_or(0, |(_, node)| node.len())
+    }
+
+    /// Find the node matching a request path, along with the captured parameters.
+    /// Static segments take priority over parameters, which take priority over wildcards.
+    pub fn find(&self, path: &str) -> Option<(&Self, HashMap<String, String>)> {
+        let mut params = Vec::new();
+
+        self.lookup(&segments(path), &mut params)
+            .map(|node| (node, params.into_iter().collect()))
+    }
+
+    fn insert_segments(&mut self, segments: &[&str]) -> &mut Self {
+        let Some((first, rest)) = segments.split_first() else {
+            return self;
+        };
+
+        if let Some(name) = first.strip_prefix('*') {
+            assert!(
+                rest.is_empty(),
+                "wildcard `{first}` must be the last segment of a route"
+            );
+
+            let (existing, node) = self
+                .wildcard
+                .get_or_insert_with(|| (name.to_string(), Box::new(Self::new())));
+            assert_eq!(existing, name, "conflicting wildcard names");
+
+            return node;
+        }
+
+        if let Some(name) = first.strip_prefix(':') {
+            let (existing, node) = self
+                .param
+                .get_or_insert_with(|| (name.to_string(), Box::new(Self::new())));
+            assert_eq!(existing, name, "conflicting parameter names");
+
+            return node.insert_segments(rest);
+        }
+
+        let statics = &segments[..segments.iter().take_while(|s| !is_dynamic(s)).count()];
+        let child = self
+            .children
+            .entry((*first).to_string())
+            .or_insert_with(|| Self {
+                prefix: statics.iter().map(ToString::to_string).collect(),
+                ..Self::new()
+            });
+
+        let common = child
+            .prefix
+            .iter()
+            .zip(statics)
+            .take_while(|(a, b)| a == *b)
+            .count();
+
+        if common < child.prefix.len() {
+            child.split(common);
+        }
+
+        child.insert_segments(&segments[common..])
+    }
+
+    /// Split this node's prefix at the given index, moving everything after it to a new child.
+    fn split(&mut self, at: usize) {
+        let child = Self {
+            prefix: self.prefix.split_off(at),
+            children: std::mem::take(&mut self.children),
+            param: self.param.take(),
+            wildcard: self.wildcard.take(),
+            endpoints: std::mem::take(&mut self.endpoints),
+            middleware: std::mem::take(&mut self.middleware),
+        };
+
+        self.children.insert(child.prefix[0].clone(), child);
+    }
+
+    fn lookup(&self, segments: &[&str], params: &mut Vec<(String, String)>) -> Option<&Self> {
+        let Some(first) = segments.first() else {
+            return (!self.endpoints.is_empty()).then_some(self);
+        };
+
+        if let Some(child) = self.children.get(*first) {
+            if segments.len() >= child.prefix.len()
+                && child.prefix.iter().zip(segments).all(|(a, b)| a == b)
+            {
+                if let Some(node) = child.lookup(&segments[child.prefix.len()..], params) {
+                    return Some(node);
+                }
+            }
+        }
+
+        if let Some((name, child)) = &self.param {
+            if !first.is_empty() {
+                params.push((name.clone(), (*first).to_string()));
+
+                if let Some(node) = child.lookup(&segments[1..], params) {
+                    return Some(node);
+                }
+
+                params.pop();
+            }
+        }
+
+        if let Some((name, child)) = &self.wildcard {
+            let rest = segments.join("/");
+
+            if !rest.is_empty() && !child.endpoints.is_empty() {
+                params.push((name.clone(), rest));
+
+                return Some(child);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use super::*;
+    use test::Bencher;
+
+    #[test]
+    fn test_insert_compresses_static_segments() {
+        let mut tree = Node::new();
+
+        tree.insert("/api/v1/users", Method::Get, 1);
+
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children["api"].prefix, vec!["api", "v1", "users"]);
+
+        tree.insert("/api/v2/users", Method::Get, 2);
+
+        let api = &tree.children["api"];
+        assert_eq!(api.prefix, vec!["api"]);
+        assert_eq!(api.children["v1"].prefix, vec!["v1", "users"]);
+        assert_eq!(api.children["v2"].prefix, vec!["v2", "users"]);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_find_static_routes() {
+        let mut tree = Node::new();
+
+        tree.insert("/", Method::Get, 1);
+        tree.insert("/api/v1/users", Method::Get, 2);
+        tree.insert("/api/v1/users", Method::Post, 3);
+        tree.insert("/api", Method::Get, 4);
+
+        let (node, _) = tree.find("/").unwrap();
+        assert_eq!(node.get(Method::Get), Some(&1));
+
+        let (node, _) = tree.find("/api/v1/users").unwrap();
+        assert_eq!(node.get(Method::Get), Some(&2));
+        assert_eq!(node.get(Method::Post), Some(&3));
+
+        let (node, _) = tree.find("/api").unwrap();
+        assert_eq!(node.get(Method::Get), Some(&4));
+
+        assert!(tree.find("/api/v1").is_none());
+        assert!(tree.find("/api/v1/users/1").is_none());
+    }
+
+    #[test]
+    fn test_find_captures_params() {
+        let mut tree = Node::new();
+
+        tree.insert("/users/:id/posts/:post", Method::Get, 1);
+
+        let (_, params) = tree.find("/users/42/posts/hello").unwrap();
+
+        assert_eq!(params.get("id"), Some(&"42".to_string()));
+        assert_eq!(params.get("post"), Some(&"hello".to_string()));
+        assert!(tree.find("/users/42/posts").is_none());
+        assert!(tree.find("/users//posts/hello").is_none());
+        assert!(tree.find("/users/42/posts/hello/extra").is_none());
+    }
+
+    #[test]
+    fn test_find_captures_wildcard() {
+        let mut tree = Node::new();
+
+        tree.insert("/files/*rest", Method::Get, 1);
+
+        let (_, params) = tree.find("/files/docs/readme.md").unwrap();
+
+        assert_eq!(params.get("rest"), Some(&"docs/readme.md".to_string()));
+        assert!(tree.find("/files").is_none());
+        assert!(tree.find("/files/").is_none());
+    }
+
+    #[test]
+    fn test_find_backtracks_to_dynamic_segments() {
+        let mut tree = Node::new();
+
+        tree.insert("/users/me/settings", Method::Get, 1);
+        tree.insert("/users/:id/posts", Method::Get, 2);
+
+        let (node, params) = tree.find("/users/me/posts").unwrap();
+
+        assert_eq!(node.get(Method::Get), Some(&2));
+        assert_eq!(params.get("id"), Some(&"me".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicting parameter names")]
+    fn test_insert_panics_on_conflicting_params() {
+        let mut tree = Node::new();
+
+        tree.insert("/users/:id", Method::Get, 1);
+        tree.insert("/users/:name/posts", Method::Get, 2);
+    }
+
+    /// Build a tree with `count` routes, spread across static, dynamic and wildcard segments.
+    fn tree_with_routes(count: usize) -> Node<usize> {
+        let mut tree = Node::new();
+
+        for i in 0..count {
+            match i % 3 {
+                0 => tree.insert(&format!("/api/resource{i}"), Method::Get, i),
+                1 => tree.insert(&format!("/api/resource{i}/:id"), Method::Get, i),
+                _ => tree.insert(&format!("/static/resource{i}/*path"), Method::Get, i),
+            }
+        }
+
+        tree
+    }
+
+    #[bench]
+    fn bench_find_10_routes(b: &mut Bencher) {
+        let tree = tree_with_routes(10);
+
+        b.iter(|| tree.find(test::black_box("/api/resource4/42")).unwrap());
+    }
+
+    #[bench]
+    fn bench_find_1_000_routes(b: &mut Bencher) {
+        let tree = tree_with_routes(1_000);
+
+        b.iter(|| tree.find(test::black_box("/api/resource994/42")).unwrap());
+    }
+
+    #[bench]
+    fn bench_find_10_000_routes(b: &mut Bencher) {
+        let tree = tree_with_routes(10_000);
+
+        b.iter(|| tree.find(test::black_box("/api/resource9994/42")).unwrap());
+    }
+}
@@ -1,8 +1,25 @@
-use crate::{http::Request, router::Router};
+#[cfg(feature = "http2")]
+use crate::http2;
+#[cfg(unix)]
+use crate::listener::UnixOptions;
+#[cfg(feature = "tls")]
+use crate::tls::{self, Tls};
+use crate::{
+    config::Config,
+    error::Error,
+    http::{IntoResponse, Method, ParseError, Parser, Request, Rewind, Upgraded, Version},
+    listener::{ConnectionInfo, Listener},
+    middleware::Middleware,
+    router::Router,
+};
 use anyhow::Result;
+use std::{future::Future, net::SocketAddr, sync::Arc};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{TcpListener, TcpStream},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, ToSocketAddrs},
+    sync::watch,
+    task::JoinSet,
+    time::timeout,
 };
 
 /// The server is responsible for accepting connections and routing requests.
@@ -10,14 +27,14 @@ use tokio::{
 /// # Example
 ///
 /// ```rust,no_run
-/// use lil_http::{Server, Response};
+/// use lil_http::{Request, Response, Server};
 ///
 /// #[tokio::main]
 /// async fn main() {
 ///    let mut http = Server::new().await.unwrap();
 ///
 ///    http.routes
-///        .get("/", |request| {
+///        .get("/", |request: Request| {
 ///           println!("Received {} request to {}", request.method, request.path);
 ///
 ///          Response::text("Hello, World!")
@@ -29,15 +46,23 @@ use tokio::{
 ///
 /// # Notes
 ///
-/// The server will not stop until the process is killed.
+/// [`Server::run`] will not stop until the process is killed. Use [`Server::run_with_shutdown`]
+/// or [`Server::run_until_signal`] to let in-flight requests finish before stopping.
 pub struct Server {
-    /// The underlying TCP listener.
-    listener: TcpListener,
+    /// The underlying listener.
+    listener: Listener,
     /// The router instance that will handle requests.
     pub routes: Router,
+    /// The limits applied to incoming requests.
+    pub config: Config,
+    /// The TLS settings, if enabled.
+    #[cfg(feature = "tls")]
+    tls: Option<Tls>,
 }
 
 impl Server {
+    /// Create a server listening on port 3000 of every interface.
+    ///
     /// # Errors
     ///
     /// Will return an error if port 3000 is already in use.
@@ -45,84 +70,693 @@ impl Server {
         Self::with_port("3000").await
     }
 
+    /// Create a server listening on the given port of every interface.
+    ///
     /// # Errors
     ///
     /// Will return an error if the port is already in use.
     pub async fn with_port(port: &str) -> Result<Self> {
-        let listener = TcpListener::bind(format!("0.0.0.0:{port}")).await?;
-        println!("Server listening on port {port}");
+        Self::bind(format!("0.0.0.0:{port}")).await
+    }
+
+    /// Create a server listening on the given address.
+    /// Binding to port 0 picks a free port, which can be found with [`Server::local_addr`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::Server;
+    ///
+    /// # async fn example() {
+    /// let loopback = Server::bind("127.0.0.1:8080").await.unwrap();
+    /// let ipv6 = Server::bind("[::1]:8080").await.unwrap();
+    /// let ephemeral = Server::bind(("localhost", 0)).await.unwrap();
+    ///
+    /// println!("Listening on {}", ephemeral.local_addr().unwrap());
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the address can't be resolved, or is already in use.
+    pub async fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
 
-        Ok(Self {
+        Ok(Self::from_listener(listener))
+    }
+
+    /// Create a server that accepts connections from an existing listener.
+    #[must_use]
+    pub fn from_listener(listener: TcpListener) -> Self {
+        Self::with_listener(Listener::Tcp(listener))
+    }
+
+    /// Create a server listening on a Unix socket at `path`.
+    ///
+    /// Handlers can identify the process on the other end through
+    /// [`ConnectionInfo::peer_credentials`](crate::ConnectionInfo::peer_credentials).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::{Server, UnixOptions};
+    ///
+    /// # async fn example() {
+    /// let http = Server::bind_unix(
+    ///     "/run/app.sock",
+    ///     UnixOptions {
+    ///         permissions: Some(0o660),
+    ///         ..UnixOptions::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the path is already in use (including by a running server),
+    /// or the socket can't be bound, or its permissions can't be set.
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl AsRef<std::path::Path>, options: UnixOptions) -> Result<Self> {
+        Ok(Self::with_listener(Listener::bind_unix(
+            path.as_ref(),
+            options,
+        )?))
+    }
+
+    fn with_listener(listener: Listener) -> Self {
+        println!("Server listening on {}", listener.describe());
+
+        Self {
             listener,
             routes: Router::new(),
-        })
+            config: Config::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
     }
 
-    /// # Panics
+    /// The address the server is listening on.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the address of the underlying listener can't be retrieved,
+    /// or if the server is listening on a Unix socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Register a middleware that runs around every request.
+    /// See [`Middleware`] for more information.
+    pub fn middleware<M>(&mut self, middleware: impl Middleware<M>) -> &mut Self {
+        self.routes.middleware(middleware);
+
+        self
+    }
+
+    /// Customize how errors returned by handlers are turned into responses.
+    /// See [`Error`] for more information.
+    pub fn error_handler<R: IntoResponse>(
+        &mut self,
+        handler: impl Fn(&Error) -> R + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.routes.error_handler(handler);
+
+        self
+    }
+
+    /// Make a value available to every handler.
+    /// See [`State`](crate::State) for more information.
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        self.routes.with_state(state);
+
+        self
+    }
+
+    /// Only accept TLS connections, using the given certificate.
+    /// See [`Tls`] for more information.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(&mut self, tls: Tls) -> &mut Self {
+        self.tls = Some(tls);
+
+        self
+    }
+
+    /// Start accepting connections and handling requests.
     ///
-    /// Will panic if the server fails to accept a connection.
+    /// Connection errors are logged, and handler panics result in a 500 response,
+    /// so a single misbehaving request won't bring the server down.
     pub async fn run(&self) {
+        self.run_with_shutdown(std::future::pending()).await;
+    }
+
+    /// Start accepting connections and handling requests, until `signal` resolves.
+    ///
+    /// Once it does, the server stops accepting connections and closes idle ones, while in-flight requests
+    /// get up to [`Config::shutdown_timeout`] to finish. It returns once every connection has been closed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::Server;
+    ///
+    /// # async fn example() {
+    /// let http = Server::new().await.unwrap();
+    ///
+    /// http.run_with_shutdown(async {
+    ///     tokio::signal::ctrl_c().await.unwrap();
+    /// })
+    /// .await;
+    /// # }
+    /// ```
+    pub async fn run_with_shutdown(&self, signal: impl Future<Output = ()> + Send) {
+        let router = Arc::new(self.routes.clone());
+        let config = Arc::new(self.config.clone());
+        let (shutdown, watcher) = watch::channel(false);
+        let mut connections = JoinSet::new();
+        #[cfg(feature = "tls")]
+        let acceptor = self.tls.as_ref().map(Tls::acceptor);
+
+        tokio::pin!(signal);
+
         loop {
-            let incoming = self.listener.accept().await;
+            tokio::select! {
+                () = &mut signal => break,
+                incoming = self.listener.accept() => match incoming {
+                    Ok((stream, info)) => {
+                        let router = router.clone();
+                        let config = config.clone();
+                        let watcher = watcher.clone();
+                        #[cfg(feature = "tls")]
+                        let acceptor = acceptor.clone();
 
-            match incoming {
-                Ok((mut stream, _)) => {
-                    let router = self.routes.clone();
+                        connections.spawn(async move {
+                            #[cfg(feature = "tls")]
+                            let (stream, info) =
+                                match tls::accept(acceptor.as_ref(), stream, info, config.idle_timeout).await {
+                                    Ok(accepted) => accepted,
+                                    Err(e) => return println!("error: TLS handshake failed: {e}"),
+                                };
 
-                    tokio::spawn(async move {
-                        Self::handle_connection(&mut stream, router).await.unwrap();
-                    });
-                }
-                Err(e) => {
-                    println!("error: {e}");
-                }
+                            if let Err(e) =
+                                Self::handle_connection(stream, Arc::new(info), router, config, watcher).await
+                            {
+                                println!("error: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        println!("error: {e}");
+                    }
+                },
+                // Reap finished connections, so they don't pile up.
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
             }
         }
+
+        _ = shutdown.send(true);
+
+        let drained = timeout(config.shutdown_timeout, async {
+            while connections.join_next().await.is_some() {}
+        })
+        .await;
+
+        if drained.is_err() {
+            println!(
+                "error: shutdown timed out, dropping {} connections",
+                connections.len()
+            );
+            connections.shutdown().await;
+        }
     }
 
-    async fn handle_connection(stream: &mut TcpStream, router: Router) -> Result<()> {
-        loop {
-            let mut buf = [0; 1024];
-            _ = stream.read(&mut buf).await?;
-            let Ok(request) = Request::try_from(&buf) else { break };
+    /// Start accepting connections and handling requests, until the process receives SIGINT or SIGTERM
+    /// (or Ctrl+C, on platforms without Unix signals). See [`Server::run_with_shutdown`] for more information.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the signal handlers can't be registered.
+    pub async fn run_until_signal(&self) {
+        self.run_with_shutdown(async {
+            #[cfg(unix)]
+            {
+                use tokio::signal::unix::{signal, SignalKind};
 
-            let response = router.handle(request);
+                let mut terminate =
+                    signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
 
-            stream.write_all(response.to_string().as_bytes()).await?;
-            stream.shutdown().await?;
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+            }
+
+            #[cfg(not(unix))]
+            {
+                _ = tokio::signal::ctrl_c().await;
+            }
+        })
+        .await;
+    }
+
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static>(
+        mut stream: S,
+        info: Arc<ConnectionInfo>,
+        router: Arc<Router>,
+        config: Arc<Config>,
+        shutdown: watch::Receiver<bool>,
+    ) -> Result<()> {
+        #[cfg(all(feature = "http2", feature = "tls"))]
+        if info
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.alpn_protocol.as_deref())
+            == Some(&b"h2"[..])
+        {
+            return http2::serve(stream, info, router, config, shutdown).await;
         }
 
+        let mut parser = Parser::new(config.clone()).with_shutdown(shutdown.clone());
+
+        for served in 1.. {
+            let request = match parser.next(&mut stream).await {
+                Ok(Some(request)) => Request {
+                    connection: info.clone(),
+                    ..request
+                },
+                Ok(None) => break,
+                Err(ParseError::Rejected(error)) => {
+                    println!("error: invalid request: {error}");
+
+                    // We can't tell where the next request would start, so the connection can't be reused.
+                    let mut response = error.into_response().header("Connection", "close");
+                    response.add_default_headers(config.server_header.as_deref());
+                    response
+                        .write_to(&mut stream, Version::Http11, false)
+                        .await?;
+                    break;
+                }
+                Err(ParseError::Io(e)) => return Err(e.into()),
+                #[cfg(feature = "http2")]
+                Err(ParseError::Http2(buffered)) => {
+                    // Switching protocols is only allowed at the start of the connection.
+                    if served > 1 {
+                        break;
+                    }
+
+                    return http2::serve(
+                        Rewind::new(buffered, stream),
+                        info,
+                        router,
+                        config,
+                        shutdown,
+                    )
+                    .await;
+                }
+            };
+
+            let version = request.version;
+            let is_head = request.method == Method::Head;
+            let keep_alive = request.keep_alive() && served < config.max_requests_per_connection;
+
+            let mut response = router.handle(request).await.check_upgrade(version);
+            response.add_default_headers(config.server_header.as_deref());
+
+            if response.is_upgrade() {
+                response.write_to(&mut stream, version, false).await?;
+                stream.flush().await?;
+
+                // Bytes the client sent after the request already belong to the new protocol.
+                let io = Upgraded::new(Rewind::new(parser.into_buffer(), stream))
+                    .with_shutdown(shutdown);
+                if let Some(upgrade) = response.upgrade {
+                    upgrade.run(io).await;
+                }
+
+                return Ok(());
+            }
+
+            let keep_alive = keep_alive
+                && !*shutdown.borrow()
+                && (is_head || response.is_framed(version))
+                && !response
+                    .header_value("Connection")
+                    .is_some_and(|connection| connection.eq_ignore_ascii_case("close"));
+
+            if !keep_alive {
+                response = response.header("Connection", "close");
+            } else if version == Version::Http10 {
+                response = response.header("Connection", "keep-alive");
+            }
+
+            response.write_to(&mut stream, version, is_head).await?;
+
+            if !keep_alive {
+                break;
+            }
+        }
+
+        stream.shutdown().await?;
+
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use serial_test::serial;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
 
     use super::*;
+    use crate::Response;
+    use tokio::net::TcpStream;
 
     #[tokio::test]
-    #[serial]
     async fn test_can_create_server() {
-        let _ = Server::new().await.unwrap();
+        let http = Server::bind("127.0.0.1:0").await.unwrap();
+
+        assert_ne!(http.local_addr().unwrap().port(), 0);
     }
 
     #[tokio::test]
-    #[serial]
     async fn test_can_create_server_with_port() {
-        let _ = Server::with_port("3001").await.unwrap();
+        let http = Server::with_port("0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+
+        assert!(addr.ip().is_unspecified());
+        assert_ne!(addr.port(), 0);
     }
 
     #[tokio::test]
-    #[serial]
     async fn test_can_run_server() {
-        let http = Server::with_port("4012").await.unwrap();
+        let http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
 
         tokio::spawn(async move {
             http.run().await;
         });
 
-        let _ = TcpStream::connect("127.0.0.1:4012").await.unwrap();
+        let _ = TcpStream::connect(addr).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_can_bind_to_any_address() {
+        let http = Server::bind(("localhost", 0)).await.unwrap();
+        assert!(http.local_addr().unwrap().ip().is_loopback());
+        assert_ne!(http.local_addr().unwrap().port(), 0);
+
+        if let Ok(http) = Server::bind("[::1]:0").await {
+            assert!(http.local_addr().unwrap().is_ipv6());
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(Server::from_listener(listener).local_addr().unwrap(), addr);
+    }
+
+    #[tokio::test]
+    async fn test_exposes_remote_address() {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.routes.get("/", |request: Request| {
+            Response::text(&format!("{:?}", request.connection().remote_addr))
+        });
+
+        tokio::spawn(async move {
+            http.run().await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let local = stream.local_addr().unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.ends_with(&format!("Some({local})")), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_hands_over_upgraded_connections() {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.routes.get("/shout", || {
+            Response::ok()
+                .header("Upgrade", "shout")
+                .header("Connection", "Upgrade")
+                .on_upgrade(|mut io| async move {
+                    let mut buf = [0; 5];
+                    io.read_exact(&mut buf).await.unwrap();
+                    io.write_all(&buf.to_ascii_uppercase()).await.unwrap();
+                })
+        });
+
+        tokio::spawn(async move {
+            http.run().await;
+        });
+
+        // Bytes sent right after the request belong to the new protocol.
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /shout HTTP/1.1\r\nUpgrade: shout\r\nConnection: Upgrade\r\n\r\nhel")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(b"lo").await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+            "{response}"
+        );
+        assert!(response.ends_with("\r\n\r\nHELLO"), "{response}");
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /shout HTTP/1.0\r\n\r\nhello")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 505 HTTP Version Not Supported\r\n"),
+            "{response}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_serves_on_unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::net::UnixStream;
+
+        let path = std::env::temp_dir().join(format!("lil-http-{}.sock", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        assert!(
+            Server::bind_unix(&path, UnixOptions::default()).is_err(),
+            "regular files shouldn't be removed"
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let mut http = Server::bind_unix(
+            &path,
+            UnixOptions {
+                permissions: Some(0o600),
+                ..UnixOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(http.local_addr().is_err());
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(
+            Listener::bind_unix(&path, UnixOptions::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::AddrInUse,
+            "a running server's socket shouldn't be removed"
+        );
+        assert!(path.exists());
+
+        http.routes.get("/", |request: Request| {
+            let credentials = request.connection().peer_credentials.unwrap();
+            Response::text(&format!("{} {:?}", credentials.uid, credentials.pid))
+        });
+
+        let (shutdown, signal) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            http.run_with_shutdown(async {
+                _ = signal.await;
+            })
+            .await;
+        });
+
+        let mut stream = UnixStream::connect(&path).await.unwrap();
+        let credentials = stream.peer_cred().unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(
+            response.ends_with(&format!(
+                "{} {:?}",
+                credentials.uid(),
+                i32::try_from(std::process::id()).ok()
+            )),
+            "{response}"
+        );
+
+        shutdown.send(()).unwrap();
+        server.await.unwrap();
+        assert!(!path.exists(), "socket file should be removed on drop");
+    }
+
+    /// Start a server that answers every request with its path,
+    /// and return everything it sends back for `requests` before closing the connection.
+    async fn exchange(config: Config, requests: &str) -> String {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.config = config;
+        http.routes
+            .r#match(vec![Method::Get, Method::Head], "/", |request: Request| {
+                Response::text(&request.path)
+            });
+
+        tokio::spawn(async move {
+            http.run().await;
+        });
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(requests.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        response
+    }
+
+    #[tokio::test]
+    async fn test_handles_pipelined_requests_on_one_connection() {
+        let response = exchange(
+            Config::default(),
+            "GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert_eq!(response.matches("Connection: close").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_leaves_the_body_out_of_head_responses() {
+        let response = exchange(
+            Config::default(),
+            "HEAD / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+
+        let (head, get) = response.split_at(response.rfind("HTTP/1.1 200 OK").unwrap());
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(head.contains("Content-Length: 1\r\n"), "{response}");
+        assert!(head.ends_with("\r\n\r\n"), "{response}");
+        assert!(get.ends_with("\r\n\r\n/"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_adds_default_headers() {
+        let config = Config {
+            server_header: Some("lil-http".to_string()),
+            ..Config::default()
+        };
+        let response = exchange(config, "GET / HTTP/1.1\r\nConnection: close\r\n\r\n").await;
+
+        assert!(response.contains("Content-Length: 1\r\n"));
+        assert!(response.contains("Server: lil-http\r\n"));
+        assert!(response.contains("Date: "));
+    }
+
+    #[tokio::test]
+    async fn test_closes_http_1_0_connections_by_default() {
+        let response = exchange(
+            Config::default(),
+            "GET / HTTP/1.0\r\n\r\nGET / HTTP/1.0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+
+        let response = exchange(
+            Config::default(),
+            "GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET / HTTP/1.0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(response.contains("Connection: keep-alive"));
+    }
+
+    #[tokio::test]
+    async fn test_closes_connections_over_the_limits() {
+        let config = Config {
+            max_requests_per_connection: 2,
+            ..Config::default()
+        };
+        let response = exchange(config, &"GET / HTTP/1.1\r\n\r\n".repeat(3)).await;
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+
+        let config = Config {
+            idle_timeout: Duration::from_millis(50),
+            ..Config::default()
+        };
+        let response = exchange(config, "GET / HTTP/1.1\r\n\r\n").await;
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        http.routes.get("/slow", || async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            "Done"
+        });
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            http.run_with_shutdown(async {
+                _ = stopped.await;
+            })
+            .await;
+        });
+
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        let mut busy = TcpStream::connect(addr).await.unwrap();
+        busy.write_all(b"GET /slow HTTP/1.1\r\n\r\n").await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        let mut response = String::new();
+        busy.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Connection: close"));
+        assert!(response.ends_with("Done"));
+
+        let mut response = String::new();
+        idle.read_to_string(&mut response).await.unwrap();
+        assert!(response.is_empty());
+
+        server.await.unwrap();
     }
 }
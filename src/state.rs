@@ -0,0 +1,106 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// The application state registered on a router, keyed by type.
+pub type StateMap = Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>;
+
+/// A handle to a piece of application state, shared by every request.
+///
+/// State is registered once with [`Server::with_state`](crate::Server::with_state), and handlers
+/// can then take it as an argument, or retrieve it with [`Request::state`](crate::Request::state).
+/// Cloning a `State` is cheap, as it only clones a reference to the underlying value.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Server, Response, State};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.with_state(AtomicUsize::new(0));
+///
+/// http.routes.get("/", |counter: State<AtomicUsize>| {
+///     let visits = counter.fetch_add(1, Ordering::SeqCst) + 1;
+///
+///     Response::text(&format!("You are visitor #{visits}"))
+/// });
+/// # }
+/// ```
+pub struct State<T>(Arc<T>);
+
+impl<T> State<T> {
+    /// Get a reference-counted pointer to the underlying value.
+    #[must_use]
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> State<T> {
+    /// Look up the state of type `T` in a state map.
+    pub(crate) fn from_map(map: &StateMap) -> Option<Self> {
+        map.get(&TypeId::of::<T>())
+            .and_then(|state| state.clone().downcast().ok())
+            .map(Self)
+    }
+}
+
+impl<T> Clone for State<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Debug> Debug for State<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("State").field(&self.0).finish()
+    }
+}
+
+/// Add a value to a state map, replacing any previous value of the same type.
+pub fn insert<T: Send + Sync + 'static>(map: &mut StateMap, state: T) {
+    Arc::make_mut(map).insert(TypeId::of::<T>(), Arc::new(state));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_from_map() {
+        let mut map = StateMap::default();
+
+        insert(&mut map, 42_usize);
+        insert(&mut map, "config".to_string());
+
+        assert_eq!(*State::<usize>::from_map(&map).unwrap(), 42);
+        assert_eq!(*State::<String>::from_map(&map).unwrap(), "config");
+        assert!(State::<u8>::from_map(&map).is_none());
+    }
+
+    #[test]
+    fn test_insert_replaces_state_of_same_type() {
+        let mut map = StateMap::default();
+
+        insert(&mut map, 1_usize);
+        insert(&mut map, 2_usize);
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(*State::<usize>::from_map(&map).unwrap(), 2);
+    }
+}
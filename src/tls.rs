@@ -0,0 +1,727 @@
+use crate::listener::{ConnectionInfo, Stream};
+use rustls::{
+    server::{
+        AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, ClientHello,
+        ResolvesServerCert,
+    },
+    sign::{self, CertifiedKey},
+    Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use rustls_pemfile::Item;
+use std::{
+    fmt::{Debug, Formatter},
+    fs,
+    io::{self, ErrorKind},
+    net::IpAddr,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+use tokio::time::timeout;
+use tokio_rustls::TlsAcceptor;
+use x509_parser::extensions::GeneralName;
+
+/// TLS settings for a [`Server`](crate::Server), which will only accept TLS connections once they're set.
+///
+/// Clones share the same certificate, so a clone kept around can be used to reload it
+/// (e.g. after it has been renewed) without restarting the server.
+/// Existing connections keep the certificate they were established with.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Server, Tls};
+///
+/// # async fn example() {
+/// let mut http = Server::bind("0.0.0.0:443").await.unwrap();
+/// let tls = Tls::from_pem_files("cert.pem", "key.pem").unwrap();
+///
+/// http.with_tls(tls.clone());
+///
+/// tokio::spawn(async move {
+///     loop {
+///         tokio::time::sleep(std::time::Duration::from_secs(24 * 60 * 60)).await;
+///
+///         if let Err(e) = tls.reload_from_pem_files("cert.pem", "key.pem") {
+///             println!("error: failed to reload certificate: {e}");
+///         }
+///     }
+/// });
+///
+/// http.run().await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tls {
+    /// The certificate presented to clients, shared between clones.
+    resolver: Arc<Resolver>,
+    /// The protocols offered through ALPN, in order of preference.
+    alpn_protocols: Vec<Vec<u8>>,
+    /// The CAs client certificates are verified against, and whether clients must present one.
+    client_auth: Option<(RootCertStore, ClientAuth)>,
+}
+
+/// Whether clients must present a certificate.
+/// See [`Tls::with_client_auth`] for more information.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ClientAuth {
+    /// Clients may connect without a certificate, but any certificate they present must be valid.
+    /// Use `require_client_cert` on [`Server::routes`](crate::Server::routes) to require one for specific routes.
+    Optional,
+    /// Clients without a valid certificate are rejected during the handshake.
+    Required,
+}
+
+impl Tls {
+    /// Load a PEM-encoded certificate chain and private key (PKCS#8, PKCS#1 or SEC1).
+    /// Only `http/1.1` (and `h2`, with the `http2` feature) is offered through ALPN, see [`Tls::with_alpn_protocols`] to change it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if no certificate or private key can be found, or the key isn't supported.
+    pub fn from_pem(certs: &[u8], key: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            resolver: Arc::new(Resolver {
+                key: RwLock::new(Arc::new(load_pem(certs, key)?)),
+            }),
+            alpn_protocols: if cfg!(feature = "http2") {
+                vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+            } else {
+                vec![b"http/1.1".to_vec()]
+            },
+            client_auth: None,
+        })
+    }
+
+    /// Load a PEM-encoded certificate chain and private key from files.
+    /// See [`Tls::from_pem`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if either file can't be read, or doesn't contain a valid certificate or key.
+    pub fn from_pem_files(certs: impl AsRef<Path>, key: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_pem(&fs::read(certs)?, &fs::read(key)?)
+    }
+
+    /// Set the protocols offered through ALPN, in order of preference.
+    /// Clients that don't use ALPN can still connect, without a negotiated protocol, but the handshake fails
+    /// for clients that only offer protocols outside of this list (RFC 7301, section 3.2).
+    #[must_use]
+    pub fn with_alpn_protocols<P: Into<Vec<u8>>>(
+        mut self,
+        protocols: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.alpn_protocols = protocols.into_iter().map(Into::into).collect();
+
+        self
+    }
+
+    /// Ask clients for a certificate signed by one of the PEM-encoded CAs in `ca_certs` (mutual TLS).
+    /// Handlers can inspect the verified certificate through [`Request::peer_certificate`](crate::Request::peer_certificate).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use lil_http::{ClientAuth, Request, Server, Tls};
+    ///
+    /// # async fn example() {
+    /// let mut http = Server::bind("0.0.0.0:443").await.unwrap();
+    /// let tls = Tls::from_pem_files("cert.pem", "key.pem")
+    ///     .unwrap()
+    ///     .with_client_auth(&std::fs::read("ca.pem").unwrap(), ClientAuth::Optional)
+    ///     .unwrap();
+    ///
+    /// http.with_tls(tls)
+    ///     .routes
+    ///     .get("/", || "Hello, anyone!")
+    ///     .get("/internal", |request: Request| {
+    ///         format!("Hello, {}!", request.peer_certificate().unwrap().subject)
+    ///     })
+    ///     .require_client_cert("/internal");
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if no valid CA certificate can be found.
+    pub fn with_client_auth(mut self, ca_certs: &[u8], mode: ClientAuth) -> io::Result<Self> {
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut &*ca_certs)? {
+            roots
+                .add(&Certificate(cert))
+                .map_err(|e| invalid(&format!("invalid CA certificate: {e}")))?;
+        }
+
+        if roots.is_empty() {
+            return Err(invalid("no CA certificates found"));
+        }
+
+        self.client_auth = Some((roots, mode));
+
+        Ok(self)
+    }
+
+    /// Replace the certificate chain and private key, for every new connection.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if no certificate or private key can be found, or the key isn't supported.
+    /// In that case, the previous certificate is kept.
+    pub fn reload_from_pem(&self, certs: &[u8], key: &[u8]) -> io::Result<()> {
+        let key = Arc::new(load_pem(certs, key)?);
+
+        *self
+            .resolver
+            .key
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = key;
+
+        Ok(())
+    }
+
+    /// Replace the certificate chain and private key with the ones in the given files.
+    /// See [`Tls::reload_from_pem`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if either file can't be read, or doesn't contain a valid certificate or key.
+    pub fn reload_from_pem_files(
+        &self,
+        certs: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        self.reload_from_pem(&fs::read(certs)?, &fs::read(key)?)
+    }
+
+    /// Build an acceptor performing handshakes with these settings.
+    pub(crate) fn acceptor(&self) -> TlsAcceptor {
+        let config = ServerConfig::builder().with_safe_defaults();
+        let config = match &self.client_auth {
+            None => config.with_no_client_auth(),
+            Some((roots, ClientAuth::Optional)) => config.with_client_cert_verifier(
+                AllowAnyAnonymousOrAuthenticatedClient::new(roots.clone()).boxed(),
+            ),
+            Some((roots, ClientAuth::Required)) => config
+                .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots.clone()).boxed()),
+        };
+
+        let mut config = config.with_cert_resolver(self.resolver.clone());
+
+        config.alpn_protocols.clone_from(&self.alpn_protocols);
+
+        TlsAcceptor::from(Arc::new(config))
+    }
+}
+
+/// Information about the TLS session a request was received on.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TlsInfo {
+    /// The host name the client asked for through SNI, if any.
+    pub server_name: Option<String>,
+    /// The protocol negotiated through ALPN, if any (e.g. `b"http/1.1"`).
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// The verified certificate the client presented, if any.
+    pub peer_certificate: Option<PeerCertificate>,
+}
+
+/// A verified client certificate.
+/// See [`Tls::with_client_auth`] for more information.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PeerCertificate {
+    /// The subject's distinguished name (e.g. `CN=billing, O=Example`).
+    pub subject: String,
+    /// The DNS names, email addresses, URIs and IP addresses the certificate is valid for.
+    pub subject_alt_names: Vec<String>,
+    /// The DER encoding of the certificate.
+    pub der: Vec<u8>,
+}
+
+impl PeerCertificate {
+    /// Extract the subject and alternative names from a DER-encoded certificate.
+    fn parse(der: &[u8]) -> io::Result<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der)
+            .map_err(|e| invalid(&format!("invalid peer certificate: {e}")))?;
+
+        let subject_alt_names = cert
+            .subject_alternative_name()
+            .map_err(|e| invalid(&format!("invalid peer certificate: {e}")))?
+            .map(|extension| {
+                extension
+                    .value
+                    .general_names
+                    .iter()
+                    .filter_map(alt_name)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            subject_alt_names,
+            der: der.to_vec(),
+        })
+    }
+}
+
+/// Format a subject alternative name, if it's of a kind worth exposing.
+fn alt_name(name: &GeneralName<'_>) -> Option<String> {
+    match name {
+        GeneralName::DNSName(name) | GeneralName::RFC822Name(name) | GeneralName::URI(name) => {
+            Some((*name).to_string())
+        }
+        GeneralName::IPAddress(ip) => <[u8; 4]>::try_from(*ip)
+            .map(IpAddr::from)
+            .or_else(|_| <[u8; 16]>::try_from(*ip).map(IpAddr::from))
+            .ok()
+            .map(|ip| ip.to_string()),
+        _ => None,
+    }
+}
+
+/// Perform the TLS handshake on a new connection if an acceptor is given, recording the session in `info`.
+///
+/// # Errors
+///
+/// Will return an error if the handshake fails, or doesn't finish within `handshake_timeout`.
+pub async fn accept(
+    acceptor: Option<&TlsAcceptor>,
+    stream: Stream,
+    mut info: ConnectionInfo,
+    handshake_timeout: Duration,
+) -> io::Result<(Stream, ConnectionInfo)> {
+    let Some(acceptor) = acceptor else {
+        return Ok((stream, info));
+    };
+
+    let stream = timeout(handshake_timeout, acceptor.accept(stream))
+        .await
+        .map_err(|_| io::Error::new(ErrorKind::TimedOut, "TLS handshake timed out"))??;
+
+    let (_, session) = stream.get_ref();
+    info.tls = Some(TlsInfo {
+        server_name: session.server_name().map(str::to_string),
+        alpn_protocol: session.alpn_protocol().map(<[u8]>::to_vec),
+        peer_certificate: session
+            .peer_certificates()
+            .and_then(<[Certificate]>::first)
+            .map(|cert| PeerCertificate::parse(&cert.0))
+            .transpose()?,
+    });
+
+    Ok((Stream::Tls(Box::new(stream)), info))
+}
+
+/// Hands out the current certificate, which can be swapped at any time.
+struct Resolver {
+    /// The certificate chain and signing key.
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for Resolver {
+    fn resolve(&self, _: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.key
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
+
+impl Debug for Resolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver").finish_non_exhaustive()
+    }
+}
+
+/// Parse a PEM-encoded certificate chain and the first private key found in `key`.
+fn load_pem(certs: &[u8], key: &[u8]) -> io::Result<CertifiedKey> {
+    let certs = rustls_pemfile::certs(&mut &*certs)?;
+    if certs.is_empty() {
+        return Err(invalid("no certificates found"));
+    }
+
+    let key = rustls_pemfile::read_all(&mut &*key)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(key),
+            _ => None,
+        })
+        .ok_or_else(|| invalid("no private key found"))?;
+
+    let key = sign::any_supported_type(&PrivateKey(key))
+        .map_err(|_| invalid("unsupported private key"))?;
+
+    Ok(CertifiedKey::new(
+        certs.into_iter().map(Certificate).collect(),
+        key,
+    ))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Request, Response, Server};
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, SanType};
+    use rustls::{ClientConfig, RootCertStore, ServerName};
+    use std::net::SocketAddr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_rustls::TlsConnector;
+
+    /// Generate a self-signed certificate for `localhost`, returning its PEM-encoded chain and key.
+    fn self_signed() -> (String, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        (
+            cert.serialize_pem().unwrap(),
+            cert.serialize_private_key_pem(),
+        )
+    }
+
+    /// Generate a CA, and a client certificate for `billing` signed by it,
+    /// returning the PEM-encoded CA and the client's certificate and key.
+    fn client_identity() -> (String, (String, String)) {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "lil-http test CA");
+        let ca = rcgen::Certificate::from_params(params).unwrap();
+
+        let mut params = CertificateParams::new(vec!["billing.internal".to_string()]);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "billing");
+        params
+            .subject_alt_names
+            .push(SanType::IpAddress("10.0.0.1".parse().unwrap()));
+        let client = rcgen::Certificate::from_params(params).unwrap();
+
+        (
+            ca.serialize_pem().unwrap(),
+            (
+                client.serialize_pem_with_signer(&ca).unwrap(),
+                client.serialize_private_key_pem(),
+            ),
+        )
+    }
+
+    /// A TLS client for the test server.
+    #[derive(Default)]
+    struct Client<'a> {
+        /// The only certificate the client trusts.
+        root: &'a str,
+        /// The protocols offered through ALPN.
+        alpn: &'a [&'a [u8]],
+        /// The certificate and key presented to the server, if any.
+        identity: Option<&'a (String, String)>,
+    }
+
+    impl Client<'_> {
+        async fn get(&self, addr: SocketAddr, path: &str) -> io::Result<String> {
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut self.root.as_bytes())? {
+                roots.add(&Certificate(cert)).unwrap();
+            }
+
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots);
+            let mut config = match self.identity {
+                None => config.with_no_client_auth(),
+                Some((cert, key)) => {
+                    let certs = rustls_pemfile::certs(&mut cert.as_bytes())?;
+                    let key = rustls_pemfile::pkcs8_private_keys(&mut key.as_bytes())?.remove(0);
+
+                    config
+                        .with_client_auth_cert(
+                            certs.into_iter().map(Certificate).collect(),
+                            PrivateKey(key),
+                        )
+                        .unwrap()
+                }
+            };
+            config.alpn_protocols = self.alpn.iter().map(|protocol| protocol.to_vec()).collect();
+
+            let stream = TcpStream::connect(addr).await?;
+            let mut stream = TlsConnector::from(Arc::new(config))
+                .connect(ServerName::try_from("localhost").unwrap(), stream)
+                .await?;
+
+            stream
+                .write_all(format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n").as_bytes())
+                .await?;
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await?;
+
+            Ok(response)
+        }
+    }
+
+    async fn serve(tls: Tls) -> SocketAddr {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+
+        http.with_tls(tls)
+            .routes
+            .get("/", |request: Request| {
+                let tls = request.connection().tls.clone().unwrap();
+
+                Response::text(&format!(
+                    "{} {}",
+                    tls.server_name.unwrap_or_default(),
+                    String::from_utf8_lossy(&tls.alpn_protocol.unwrap_or_default())
+                ))
+            })
+            .get("/whoami", |request: Request| {
+                request.peer_certificate().map_or_else(
+                    || "anonymous".to_string(),
+                    |cert| format!("{} {}", cert.subject, cert.subject_alt_names.join(",")),
+                )
+            })
+            .get("/internal", || "secret")
+            .require_client_cert("/internal");
+
+        tokio::spawn(async move {
+            http.run().await;
+        });
+
+        addr
+    }
+
+    #[test]
+    fn test_rejects_invalid_pem() {
+        let (cert, key) = self_signed();
+
+        assert!(Tls::from_pem(cert.as_bytes(), key.as_bytes()).is_ok());
+        assert!(Tls::from_pem(b"", key.as_bytes()).is_err());
+        assert!(Tls::from_pem(cert.as_bytes(), b"").is_err());
+        assert!(Tls::from_pem(key.as_bytes(), cert.as_bytes()).is_err());
+
+        let tls = Tls::from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        assert!(tls.with_client_auth(b"", ClientAuth::Required).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serves_over_tls() {
+        let (cert, key) = self_signed();
+        let addr = serve(Tls::from_pem(cert.as_bytes(), key.as_bytes()).unwrap()).await;
+
+        for (alpn, expected) in [
+            (&[&b"lil/1"[..], b"http/1.1"][..], "localhost http/1.1"),
+            (&[], "localhost "),
+        ] {
+            let client = Client {
+                root: &cert,
+                alpn,
+                ..Client::default()
+            };
+
+            let response = client.get(addr, "/").await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+            assert!(response.ends_with(expected), "{response}");
+        }
+
+        // Clients offering only protocols we don't speak are turned away during the handshake.
+        let client = Client {
+            root: &cert,
+            alpn: &[b"lil/1"],
+            ..Client::default()
+        };
+        assert!(client.get(addr, "/").await.is_err());
+
+        // Without client authentication, every route requiring a certificate is off limits.
+        let client = Client {
+            root: &cert,
+            ..Client::default()
+        };
+        let response = client.get(addr, "/internal").await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+            "{response}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_negotiates_custom_protocols() {
+        let (cert, key) = self_signed();
+        let tls = Tls::from_pem(cert.as_bytes(), key.as_bytes())
+            .unwrap()
+            .with_alpn_protocols(["lil/1", "http/1.1"]);
+        let addr = serve(tls).await;
+
+        let response = Client {
+            root: &cert,
+            alpn: &[b"http/1.1", b"lil/1"],
+            ..Client::default()
+        }
+        .get(addr, "/")
+        .await
+        .unwrap();
+        assert!(response.ends_with("localhost lil/1"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_reloads_certificate() {
+        let (cert, key) = self_signed();
+        let tls = Tls::from_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        let addr = serve(tls.clone()).await;
+        let (renewed, renewed_key) = self_signed();
+        let old_client = Client {
+            root: &cert,
+            ..Client::default()
+        };
+        let new_client = Client {
+            root: &renewed,
+            ..Client::default()
+        };
+
+        assert!(old_client.get(addr, "/").await.is_ok());
+
+        assert!(tls.reload_from_pem(b"", renewed_key.as_bytes()).is_err());
+        assert!(old_client.get(addr, "/").await.is_ok());
+
+        tls.reload_from_pem(renewed.as_bytes(), renewed_key.as_bytes())
+            .unwrap();
+        assert!(old_client.get(addr, "/").await.is_err());
+        assert!(new_client.get(addr, "/").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_plaintext_requests() {
+        let (cert, key) = self_signed();
+        let addr = serve(Tls::from_pem(cert.as_bytes(), key.as_bytes()).unwrap()).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = Vec::new();
+        _ = stream.read_to_end(&mut response).await;
+        assert!(!response.starts_with(b"HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_requires_client_certificates() {
+        let (cert, key) = self_signed();
+        let (ca, identity) = client_identity();
+        let (_, untrusted) = client_identity();
+        let tls = Tls::from_pem(cert.as_bytes(), key.as_bytes())
+            .unwrap()
+            .with_client_auth(ca.as_bytes(), ClientAuth::Required)
+            .unwrap();
+        let addr = serve(tls).await;
+
+        let client = Client {
+            root: &cert,
+            identity: Some(&identity),
+            ..Client::default()
+        };
+        let response = client.get(addr, "/whoami").await.unwrap();
+        assert!(
+            response.ends_with("CN=billing billing.internal,10.0.0.1"),
+            "{response}"
+        );
+
+        let anonymous = Client {
+            root: &cert,
+            ..Client::default()
+        };
+        assert!(anonymous.get(addr, "/whoami").await.is_err());
+
+        let untrusted = Client {
+            root: &cert,
+            identity: Some(&untrusted),
+            ..Client::default()
+        };
+        assert!(untrusted.get(addr, "/whoami").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requires_client_certificates_per_route() {
+        let (cert, key) = self_signed();
+        let (ca, identity) = client_identity();
+        let tls = Tls::from_pem(cert.as_bytes(), key.as_bytes())
+            .unwrap()
+            .with_client_auth(ca.as_bytes(), ClientAuth::Optional)
+            .unwrap();
+        let addr = serve(tls).await;
+        let anonymous = Client {
+            root: &cert,
+            ..Client::default()
+        };
+        let client = Client {
+            root: &cert,
+            identity: Some(&identity),
+            ..Client::default()
+        };
+
+        let response = anonymous.get(addr, "/whoami").await.unwrap();
+        assert!(response.ends_with("anonymous"), "{response}");
+
+        let response = anonymous.get(addr, "/internal").await.unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+            "{response}"
+        );
+
+        let response = client.get(addr, "/internal").await.unwrap();
+        assert!(response.ends_with("secret"), "{response}");
+    }
+
+    #[cfg(feature = "http2")]
+    #[tokio::test]
+    async fn test_negotiates_http2() {
+        let (cert, key) = self_signed();
+        let addr = serve(Tls::from_pem(cert.as_bytes(), key.as_bytes()).unwrap()).await;
+
+        let mut roots = RootCertStore::empty();
+        for cert in rustls_pemfile::certs(&mut cert.as_bytes()).unwrap() {
+            roots.add(&Certificate(cert)).unwrap();
+        }
+
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .unwrap();
+
+        let (client, connection) = h2::client::handshake(stream).await.unwrap();
+        tokio::spawn(async move {
+            _ = connection.await;
+        });
+
+        let request = http::Request::get("https://localhost/").body(()).unwrap();
+        let (response, _) = client
+            .ready()
+            .await
+            .unwrap()
+            .send_request(request, true)
+            .unwrap();
+        let (parts, mut body) = response.await.unwrap().into_parts();
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+
+        assert_eq!(parts.version, http::Version::HTTP_2);
+        assert_eq!(bytes, b"localhost h2");
+    }
+}
@@ -0,0 +1,199 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io;
+
+/// The bytes every message ends with after a sync flush, which are left out on the wire (RFC 7692, section 7.2.1).
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+
+/// The `Sec-WebSocket-Extensions` value sent back when compression is negotiated, before any parameters echoed from the offer.
+///
+/// Compressing every message from scratch means we don't have to keep a window around for each connection,
+/// at the cost of a slightly worse compression ratio.
+const RESPONSE: &str = "permessage-deflate; server_no_context_takeover";
+
+/// Pick the first offer in a `Sec-WebSocket-Extensions` header that is a `permessage-deflate` one we can accept,
+/// returning the value to send back.
+/// See [RFC 7692](https://www.rfc-editor.org/rfc/rfc7692#section-7.1) for more information.
+pub fn negotiate(extensions: &str) -> Option<String> {
+    extensions.split(',').find_map(|offer| {
+        let mut params = offer.split(';').map(str::trim);
+        if params.next() != Some("permessage-deflate") {
+            return None;
+        }
+
+        let mut response = RESPONSE.to_string();
+        let mut seen = Vec::new();
+        for param in params {
+            let (name, value) = param
+                .split_once('=')
+                .map_or((param, None), |(name, value)| {
+                    (name.trim(), Some(value.trim().trim_matches('"')))
+                });
+
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover" | "client_no_context_takeover", None) => {}
+                // We always compress with the largest window, so we can't honour a smaller one.
+                // Accepting the parameter means echoing it back (RFC 7692, section 7.1.2.1).
+                ("server_max_window_bits", Some("15")) => {
+                    response.push_str("; server_max_window_bits=15");
+                }
+                // Clients may use any window, since we can always inflate with the largest one.
+                ("client_max_window_bits", bits)
+                    if bits.is_none_or(|bits| matches!(bits.parse::<u8>(), Ok(8..=15))) => {}
+                _ => return None,
+            }
+        }
+
+        Some(response)
+    })
+}
+
+/// Compresses outgoing messages.
+#[derive(Debug)]
+pub struct Deflater(Compress);
+
+impl Deflater {
+    /// Create a deflater using the default compression level.
+    pub fn new() -> Self {
+        Self(Compress::new(Compression::default(), false))
+    }
+
+    /// Compress a whole message.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if compression fails.
+    pub fn deflate(&mut self, message: &[u8]) -> io::Result<Vec<u8>> {
+        self.0.reset();
+
+        let mut output = Vec::with_capacity(message.len() / 2 + 64);
+        loop {
+            let consumed = total(self.0.total_in());
+            self.0
+                .compress_vec(&message[consumed..], &mut output, FlushCompress::Sync)
+                .map_err(io::Error::other)?;
+
+            if total(self.0.total_in()) == message.len() && output.len() < output.capacity() {
+                break;
+            }
+
+            output.reserve(message.len().max(64));
+        }
+
+        if output.ends_with(&TRAILER) {
+            output.truncate(output.len() - TRAILER.len());
+        }
+
+        Ok(output)
+    }
+}
+
+/// Decompresses incoming messages, keeping the window across them unless the client resets it.
+#[derive(Debug)]
+pub struct Inflater(Decompress);
+
+impl Inflater {
+    /// Create an inflater with an empty window.
+    pub fn new() -> Self {
+        Self(Decompress::new(false))
+    }
+
+    /// Decompress a whole message, returning `None` if it would inflate past `limit` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the message isn't valid deflate data.
+    pub fn inflate(&mut self, message: &[u8], limit: usize) -> io::Result<Option<Vec<u8>>> {
+        let mut input = message.to_vec();
+        input.extend_from_slice(&TRAILER);
+
+        let mut consumed = 0;
+        let mut output = Vec::with_capacity((message.len() * 2).clamp(64, limit.max(64)));
+        loop {
+            if output.len() == output.capacity() {
+                if output.len() > limit {
+                    return Ok(None);
+                }
+
+                output.reserve((limit + 1 - output.len()).min(output.len().max(64)));
+            }
+
+            let before = self.0.total_in();
+            let status = self
+                .0
+                .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(io::Error::other)?;
+            consumed += total(self.0.total_in() - before);
+
+            if output.len() > limit {
+                return Ok(None);
+            }
+
+            if status == Status::StreamEnd
+                || (consumed == input.len() && output.len() < output.capacity())
+            {
+                return Ok(Some(output));
+            }
+        }
+    }
+}
+
+/// Convert a byte count reported by flate2 into a `usize`.
+fn total(bytes: u64) -> usize {
+    usize::try_from(bytes).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiates_compression() {
+        assert_eq!(negotiate("permessage-deflate").as_deref(), Some(RESPONSE));
+        assert_eq!(
+            negotiate("permessage-deflate; client_max_window_bits").as_deref(),
+            Some(RESPONSE)
+        );
+        assert_eq!(
+            negotiate("x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=15")
+                .as_deref(),
+            Some("permessage-deflate; server_no_context_takeover; server_max_window_bits=15")
+        );
+        assert_eq!(
+            negotiate("permessage-deflate; server_max_window_bits=10, permessage-deflate")
+                .as_deref(),
+            Some(RESPONSE)
+        );
+        assert_eq!(
+            negotiate("permessage-deflate; server_max_window_bits=10"),
+            None
+        );
+        assert_eq!(negotiate("permessage-deflate; unknown_param"), None);
+        assert_eq!(
+            negotiate("permessage-deflate; server_no_context_takeover; server_no_context_takeover"),
+            None
+        );
+        assert_eq!(negotiate("x-webkit-deflate-frame"), None);
+    }
+
+    #[test]
+    fn test_round_trips_messages() {
+        let mut deflater = Deflater::new();
+        let mut inflater = Inflater::new();
+
+        for message in [&b"Hello"[..], &[b'a'; 10_000], b""] {
+            let compressed = deflater.deflate(message).unwrap();
+            assert_eq!(
+                inflater.inflate(&compressed, 10_000).unwrap().unwrap(),
+                message
+            );
+        }
+
+        let compressed = deflater.deflate(&[b'a'; 10_000]).unwrap();
+        assert_eq!(inflater.inflate(&compressed, 1000).unwrap(), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The close code for a connection closed normally.
+pub const NORMAL: u16 = 1000;
+/// The close code for a server that is shutting down.
+pub const GOING_AWAY: u16 = 1001;
+/// The close code for a peer that violated the protocol.
+pub const PROTOCOL_ERROR: u16 = 1002;
+/// The close code for a message whose data doesn't match its type (e.g. text that isn't valid UTF-8).
+pub const INVALID_DATA: u16 = 1007;
+/// The close code for a frame or message exceeding the configured limits.
+pub const TOO_BIG: u16 = 1009;
+/// The close code for a handler that failed.
+pub const INTERNAL_ERROR: u16 = 1011;
+
+/// The kind of a frame.
+/// See [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-5.2) for more information.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OpCode {
+    /// Continues a fragmented message.
+    Continuation,
+    /// Starts a text message.
+    Text,
+    /// Starts a binary message.
+    Binary,
+    /// Closes the connection.
+    Close,
+    /// Checks the connection is still alive.
+    Ping,
+    /// Answers a ping.
+    Pong,
+}
+
+impl OpCode {
+    /// Parse the opcode of a frame, returning `None` for reserved values.
+    const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x0 => Some(Self::Continuation),
+            0x1 => Some(Self::Text),
+            0x2 => Some(Self::Binary),
+            0x8 => Some(Self::Close),
+            0x9 => Some(Self::Ping),
+            0xA => Some(Self::Pong),
+            _ => None,
+        }
+    }
+
+    /// The value of the opcode on the wire.
+    const fn as_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    /// Whether frames of this kind control the connection, rather than carrying a message.
+    pub const fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// A single frame received from the client.
+#[derive(Debug)]
+pub struct Frame {
+    /// Whether this is the last frame of its message.
+    pub fin: bool,
+    /// Whether the message was compressed, for the first frame of a message.
+    pub rsv1: bool,
+    /// The kind of the frame.
+    pub opcode: OpCode,
+    /// The unmasked payload.
+    pub payload: Vec<u8>,
+}
+
+/// An error encountered while reading from the client.
+#[derive(Debug)]
+pub enum FrameError {
+    /// The connection failed.
+    Io(io::Error),
+    /// The client broke the protocol, and the connection should be closed with the given code and reason.
+    Protocol(u16, &'static str),
+}
+
+impl From<io::Error> for FrameError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Read the next frame from the client, rejecting frames with payloads larger than `max_size`.
+///
+/// # Errors
+///
+/// Will return an error if the connection fails, or if the frame is malformed, unmasked, or too large.
+pub async fn read<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<Frame, FrameError> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).await?;
+
+    let fin = head[0] & 0x80 != 0;
+    let rsv1 = head[0] & 0x40 != 0;
+    let Some(opcode) = OpCode::from_u8(head[0] & 0x0F) else {
+        return Err(FrameError::Protocol(PROTOCOL_ERROR, "unknown opcode"));
+    };
+
+    // No extension we support defines RSV2 or RSV3.
+    if head[0] & 0x30 != 0 {
+        return Err(FrameError::Protocol(
+            PROTOCOL_ERROR,
+            "unexpected reserved bits",
+        ));
+    }
+
+    // Clients must mask every frame, so intermediaries can't be tricked into caching them (RFC 6455, section 5.1).
+    if head[1] & 0x80 == 0 {
+        return Err(FrameError::Protocol(
+            PROTOCOL_ERROR,
+            "frames from the client must be masked",
+        ));
+    }
+
+    let length = match head[1] & 0x7F {
+        126 => u64::from(reader.read_u16().await?),
+        127 => reader.read_u64().await?,
+        length => u64::from(length),
+    };
+
+    if opcode.is_control() && (!fin || length > 125) {
+        return Err(FrameError::Protocol(
+            PROTOCOL_ERROR,
+            "control frames can't be fragmented or longer than 125 bytes",
+        ));
+    }
+
+    let length = usize::try_from(length)
+        .ok()
+        .filter(|length| *length <= max_size)
+        .ok_or(FrameError::Protocol(TOO_BIG, "frame too large"))?;
+
+    let mut mask = [0; 4];
+    reader.read_exact(&mut mask).await?;
+
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload).await?;
+    for (byte, key) in payload.iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= key;
+    }
+
+    Ok(Frame {
+        fin,
+        rsv1,
+        opcode,
+        payload,
+    })
+}
+
+/// Encode a complete, unmasked frame to send to the client.
+pub fn encode(opcode: OpCode, rsv1: bool, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(payload.len() + 10);
+    bytes.push(0x80 | u8::from(rsv1) << 6 | opcode.as_u8());
+
+    match (u8::try_from(payload.len()), u16::try_from(payload.len())) {
+        (Ok(length @ 0..=125), _) => bytes.push(length),
+        (_, Ok(length)) => {
+            bytes.push(126);
+            bytes.extend_from_slice(&length.to_be_bytes());
+        }
+        _ => {
+            bytes.push(127);
+            bytes.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+    }
+
+    bytes.extend_from_slice(payload);
+
+    bytes
+}
+
+/// Build a short masked frame, the way a client would send it.
+#[cfg(test)]
+pub fn masked(first: u8, payload: &[u8]) -> Vec<u8> {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let mut frame = vec![first, 0x80 | u8::try_from(payload.len()).unwrap()];
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, k)| b ^ k));
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reads_masked_frames() {
+        // The example from RFC 6455, section 5.7.
+        let mut bytes = &[
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ][..];
+        let frame = read(&mut bytes, 1024).await.unwrap();
+
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, OpCode::Text);
+        assert_eq!(frame.payload, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_frames() {
+        let cases: [(Vec<u8>, u16); 5] = [
+            (
+                vec![0x81, 0x05, b'H', b'e', b'l', b'l', b'o'],
+                PROTOCOL_ERROR,
+            ),
+            (masked(0x83, b""), PROTOCOL_ERROR),
+            (masked(0xA1, b""), PROTOCOL_ERROR),
+            (masked(0x09, b""), PROTOCOL_ERROR),
+            (masked(0x82, &[0; 64]), TOO_BIG),
+        ];
+
+        for (bytes, code) in cases {
+            match read(&mut &bytes[..], 32).await {
+                Err(FrameError::Protocol(got, _)) => assert_eq!(got, code),
+                other => panic!("expected a protocol error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_encodes_lengths() {
+        assert_eq!(encode(OpCode::Text, false, b"Hello")[..2], [0x81, 5]);
+        assert_eq!(
+            encode(OpCode::Binary, true, &[0; 256])[..4],
+            [0xC2, 126, 1, 0]
+        );
+        assert_eq!(
+            encode(OpCode::Binary, false, &vec![0; 65536])[..10],
+            [0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0]
+        );
+    }
+}
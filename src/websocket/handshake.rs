@@ -0,0 +1,255 @@
+use crate::{
+    error::Error,
+    http::{IntoResponse, Request, Response, StatusCode, Version},
+};
+
+/// Appended to the client's key before hashing it, to prove the server understood the handshake.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The characters used by the standard base64 alphabet.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Answer the opening handshake, with a 101 Switching Protocols response if the request is valid,
+/// or with the error to send back otherwise.
+/// See [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-4.2) for more information.
+pub fn response(request: &Request) -> Response {
+    let has_token = |name: &str, token: &str| {
+        request.headers.get(name).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|value| value.trim().eq_ignore_ascii_case(token))
+        })
+    };
+
+    if request.version != Version::Http11 || !has_token("upgrade", "websocket") {
+        return Error::new(
+            StatusCode::UpgradeRequired,
+            "this endpoint only accepts WebSocket connections",
+        )
+        .into_response()
+        .header("Upgrade", "websocket")
+        .header("Connection", "Upgrade");
+    }
+
+    if !has_token("connection", "upgrade") {
+        return Error::new(
+            StatusCode::BadRequest,
+            "the Connection header must include upgrade",
+        )
+        .into_response();
+    }
+
+    if request
+        .headers
+        .get("sec-websocket-version")
+        .map(String::as_str)
+        != Some("13")
+    {
+        return Error::new(
+            StatusCode::UpgradeRequired,
+            "only version 13 of the WebSocket protocol is supported",
+        )
+        .into_response()
+        .header("Sec-WebSocket-Version", "13");
+    }
+
+    let Some(key) = request
+        .headers
+        .get("sec-websocket-key")
+        .filter(|key| base64_decode(key).is_some_and(|nonce| nonce.len() == 16))
+    else {
+        return Error::new(
+            StatusCode::BadRequest,
+            "the Sec-WebSocket-Key header must be a base64-encoded 16-byte nonce",
+        )
+        .into_response();
+    };
+
+    Response::ok()
+        .status(StatusCode::SwitchingProtocols)
+        .header("Upgrade", "websocket")
+        .header("Connection", "Upgrade")
+        .header("Sec-WebSocket-Accept", &accept_key(key))
+}
+
+/// The `Sec-WebSocket-Accept` value for the given `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+/// Hash `data` with SHA-1, as described in [RFC 3174](https://www.rfc-editor.org/rfc/rfc3174).
+/// SHA-1 is broken for security purposes, but the handshake only uses it to prove the server understood the request.
+#[allow(clippy::many_single_char_names)] // Named after the variables in the RFC.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, hashed) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(hashed);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+
+    digest
+}
+
+/// Encode `data` with the standard, padded base64 alphabet.
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(group >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decode a string encoded with the standard, padded base64 alphabet.
+/// Returns `None` if the string isn't valid base64.
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(u32::from(c - b'A')),
+        b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+        b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let groups = encoded.len() / 4;
+    let mut decoded = Vec::with_capacity(groups * 3);
+
+    for (index, chunk) in encoded.chunks_exact(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+
+        // Padding is only allowed at the end of the string.
+        if padding > 2 || (padding > 0 && index + 1 != groups) {
+            return None;
+        }
+
+        let mut group = 0;
+        for &c in &chunk[..4 - padding] {
+            group = group << 6 | sextet(c)?;
+        }
+        group <<= 6 * padding;
+
+        decoded.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_computes_accept_key() {
+        // The example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_hashes_with_sha1() {
+        let hex = |digest: [u8; 20]| {
+            digest
+                .iter()
+                .fold(String::new(), |hex, b| hex + &format!("{b:02x}"))
+        };
+
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn test_round_trips_base64() {
+        for (decoded, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(decoded), encoded);
+            assert_eq!(base64_decode(encoded).as_deref(), Some(decoded));
+        }
+
+        assert_eq!(base64_decode("Zm9"), None);
+        assert_eq!(base64_decode("Zg==Zg=="), None);
+        assert_eq!(base64_decode("Z!=="), None);
+    }
+}
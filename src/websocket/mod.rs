@@ -0,0 +1,746 @@
+use std::{future::Future, io, sync::Arc};
+use tokio::{
+    io::{AsyncWriteExt, BufReader, ReadHalf, WriteHalf},
+    sync::Mutex,
+};
+
+use crate::{
+    error::Error,
+    http::{Request, Response, StatusCode, Upgraded},
+};
+use frame::{FrameError, OpCode};
+
+#[cfg(feature = "deflate")]
+mod deflate;
+mod frame;
+mod handshake;
+
+/// Limits and settings applied to WebSocket connections, registered with `routes.websocket_with_config`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct WebSocketConfig {
+    /// The maximum size of a single frame, in bytes.
+    pub max_frame_size: usize,
+    /// The maximum size of a message (after joining its frames and decompressing it), in bytes.
+    pub max_message_size: usize,
+    /// Whether to compress messages with `permessage-deflate`, if the client supports it.
+    #[cfg(feature = "deflate")]
+    pub compression: bool,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_frame_size: 1024 * 1024,
+            max_message_size: 2 * 1024 * 1024,
+            #[cfg(feature = "deflate")]
+            compression: false,
+        }
+    }
+}
+
+/// A message sent or received over a WebSocket connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Message {
+    /// A UTF-8 text message.
+    Text(String),
+    /// A binary message.
+    Binary(Vec<u8>),
+    /// A ping, with up to 125 bytes of data. Pings from the client are answered automatically.
+    Ping(Vec<u8>),
+    /// An answer to a ping, with up to 125 bytes of data.
+    Pong(Vec<u8>),
+    /// A request to close the connection, optionally with a reason.
+    /// Closing frames from the client are answered automatically.
+    Close(Option<CloseFrame>),
+}
+
+/// The reason a WebSocket connection was closed.
+/// See [RFC 6455](https://www.rfc-editor.org/rfc/rfc6455#section-7.4) for the meaning of each code.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CloseFrame {
+    /// The status code (e.g. 1000 for a normal closure).
+    pub code: u16,
+    /// A human-readable reason, of up to 123 bytes.
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// Parse the payload of a closing frame from the client.
+    fn parse(payload: &[u8]) -> Result<Option<Self>, FrameError> {
+        let Some((code, reason)) = payload.split_first_chunk::<2>() else {
+            return if payload.is_empty() {
+                Ok(None)
+            } else {
+                Err(FrameError::Protocol(
+                    frame::PROTOCOL_ERROR,
+                    "invalid close frame",
+                ))
+            };
+        };
+
+        // Codes which are reserved, or only meant to be reported locally, can't be sent (RFC 6455, section 7.4.1).
+        let code = u16::from_be_bytes(*code);
+        if !matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999) {
+            return Err(FrameError::Protocol(
+                frame::PROTOCOL_ERROR,
+                "invalid close code",
+            ));
+        }
+
+        let reason = String::from_utf8(reason.to_vec()).map_err(|_| {
+            FrameError::Protocol(frame::INVALID_DATA, "close reason is not valid UTF-8")
+        })?;
+
+        Ok(Some(Self { code, reason }))
+    }
+}
+
+/// A WebSocket connection, handed to the handlers registered with `routes.websocket`.
+///
+/// Use [`WebSocket::recv`] and [`WebSocket::send`] to exchange messages, or [`WebSocket::split`]
+/// to do both from different tasks. Pings and closing frames from the client are answered automatically,
+/// and the connection is closed once the handler returns. When the server shuts down, the connection is
+/// closed with code 1001 (going away), and the handler gets the client's answer from [`WebSocket::recv`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use lil_http::{Message, Request, Server, WebSocket};
+///
+/// # async fn example() {
+/// let mut http = Server::new().await.unwrap();
+///
+/// http.routes.websocket("/echo", |_: Request, mut socket: WebSocket| async move {
+///     while let Some(message) = socket.recv().await? {
+///         if let Message::Text(_) | Message::Binary(_) = message {
+///             socket.send(message).await?;
+///         }
+///     }
+///
+///     Ok(())
+/// });
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WebSocket {
+    /// The sending half of the connection.
+    sender: WebSocketSender,
+    /// The receiving half of the connection.
+    receiver: WebSocketReceiver,
+}
+
+impl WebSocket {
+    /// Wrap an upgraded connection.
+    fn new(io: Upgraded, config: WebSocketConfig, compressed: bool) -> Self {
+        let (reader, writer) = tokio::io::split(io);
+
+        let sender = WebSocketSender {
+            writer: Arc::new(Mutex::new(Writer {
+                io: writer,
+                closing: false,
+                #[cfg(feature = "deflate")]
+                deflater: compressed.then(deflate::Deflater::new),
+            })),
+        };
+
+        Self {
+            receiver: WebSocketReceiver {
+                reader: BufReader::new(reader),
+                sender: sender.clone(),
+                config,
+                closed: false,
+                partial: None,
+                compressed,
+                #[cfg(feature = "deflate")]
+                inflater: compressed.then(deflate::Inflater::new),
+            },
+            sender,
+        }
+    }
+
+    /// Receive the next message from the client.
+    /// See [`WebSocketReceiver::recv`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the connection fails, or if the client breaks the protocol.
+    pub async fn recv(&mut self) -> io::Result<Option<Message>> {
+        self.receiver.recv().await
+    }
+
+    /// Send a message to the client.
+    /// See [`WebSocketSender::send`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the connection fails, or is already closing.
+    pub async fn send(&self, message: Message) -> io::Result<()> {
+        self.sender.send(message).await
+    }
+
+    /// Split the connection into halves that can be used from different tasks.
+    #[must_use]
+    pub fn split(self) -> (WebSocketSender, WebSocketReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
+/// The sending half of a [`WebSocket`]. Clones send over the same connection.
+#[derive(Debug, Clone)]
+pub struct WebSocketSender {
+    /// The connection, shared with the receiving half so it can answer pings and closing frames.
+    writer: Arc<Mutex<Writer>>,
+}
+
+impl WebSocketSender {
+    /// Send a message to the client.
+    /// Sending a [`Message::Close`] starts closing the connection, after which no more messages can be sent.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the connection fails or is already closing,
+    /// or if a control message is longer than 125 bytes.
+    pub async fn send(&self, message: Message) -> io::Result<()> {
+        self.writer.lock().await.send(message).await
+    }
+
+    /// Start closing the connection with the given code, unless it's already closing.
+    async fn close(&self, code: u16, reason: &str) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        if writer.closing {
+            return Ok(());
+        }
+
+        writer
+            .send(Message::Close(Some(CloseFrame {
+                code,
+                reason: reason.to_string(),
+            })))
+            .await
+    }
+
+    /// Answer a ping, unless the connection is already closing.
+    async fn pong(&self, payload: Vec<u8>) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        if writer.closing {
+            return Ok(());
+        }
+
+        writer.send(Message::Pong(payload)).await
+    }
+
+    /// Close the connection once the handler is done with it.
+    async fn finish(&self, code: u16) -> io::Result<()> {
+        self.close(code, "").await?;
+
+        self.writer.lock().await.io.shutdown().await
+    }
+}
+
+/// The connection and compression state shared by every sender.
+#[derive(Debug)]
+struct Writer {
+    /// The writing half of the connection.
+    io: WriteHalf<Upgraded>,
+    /// Whether a closing frame has been sent.
+    closing: bool,
+    /// Compresses data messages, if negotiated.
+    #[cfg(feature = "deflate")]
+    deflater: Option<deflate::Deflater>,
+}
+
+impl Writer {
+    /// Encode and send a message as a single frame.
+    async fn send(&mut self, message: Message) -> io::Result<()> {
+        if self.closing {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the WebSocket connection is closing",
+            ));
+        }
+
+        let (opcode, payload) = match message {
+            Message::Text(text) => (OpCode::Text, text.into_bytes()),
+            Message::Binary(data) => (OpCode::Binary, data),
+            Message::Ping(data) => (OpCode::Ping, data),
+            Message::Pong(data) => (OpCode::Pong, data),
+            Message::Close(frame) => {
+                let payload = frame.map_or_else(Vec::new, |frame| {
+                    let mut payload = frame.code.to_be_bytes().to_vec();
+                    payload.extend_from_slice(frame.reason.as_bytes());
+                    payload
+                });
+
+                (OpCode::Close, payload)
+            }
+        };
+
+        if opcode.is_control() && payload.len() > 125 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "control messages can't be longer than 125 bytes",
+            ));
+        }
+
+        #[cfg(feature = "deflate")]
+        if let Some(deflater) = self.deflater.as_mut().filter(|_| !opcode.is_control()) {
+            let compressed = deflater.deflate(&payload)?;
+            return self.write(opcode, true, &compressed).await;
+        }
+
+        self.write(opcode, false, &payload).await
+    }
+
+    /// Write a single frame to the connection.
+    async fn write(&mut self, opcode: OpCode, compressed: bool, payload: &[u8]) -> io::Result<()> {
+        self.closing |= opcode == OpCode::Close;
+
+        self.io
+            .write_all(&frame::encode(opcode, compressed, payload))
+            .await?;
+        self.io.flush().await
+    }
+}
+
+/// The receiving half of a [`WebSocket`].
+#[derive(Debug)]
+pub struct WebSocketReceiver {
+    /// The reading half of the connection.
+    reader: BufReader<ReadHalf<Upgraded>>,
+    /// Used to answer pings and closing frames.
+    sender: WebSocketSender,
+    /// The limits applied to incoming frames and messages.
+    config: WebSocketConfig,
+    /// Whether the connection has been closed, and no more messages will arrive.
+    closed: bool,
+    /// The kind, compression and payload of a fragmented message that hasn't been fully received yet.
+    partial: Option<(OpCode, bool, Vec<u8>)>,
+    /// Whether `permessage-deflate` was negotiated.
+    compressed: bool,
+    /// Decompresses data messages, if negotiated.
+    #[cfg(feature = "deflate")]
+    inflater: Option<deflate::Inflater>,
+}
+
+impl WebSocketReceiver {
+    /// Receive the next message from the client.
+    /// Returns `None` once the connection has been closed.
+    ///
+    /// If the client breaks the protocol (for example, by sending an unmasked frame, or a message exceeding the
+    /// [configured limits](WebSocketConfig)), the connection is closed with the appropriate code and an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the connection fails, or if the client breaks the protocol.
+    pub async fn recv(&mut self) -> io::Result<Option<Message>> {
+        if self.closed {
+            return Ok(None);
+        }
+
+        match self.read_message().await {
+            Ok(message) => {
+                self.closed = matches!(message, Message::Close(_));
+
+                Ok(Some(message))
+            }
+            Err(FrameError::Io(error)) => {
+                self.closed = true;
+
+                Err(error)
+            }
+            Err(FrameError::Protocol(code, reason)) => {
+                self.closed = true;
+                _ = self.sender.close(code, reason).await;
+
+                Err(io::Error::new(io::ErrorKind::InvalidData, reason))
+            }
+        }
+    }
+
+    /// Read frames until a complete message (or a control frame) arrives.
+    async fn read_message(&mut self) -> Result<Message, FrameError> {
+        loop {
+            let frame = frame::read(&mut self.reader, self.config.max_frame_size).await?;
+
+            // Only the first frame of a data message can be marked as compressed (RFC 7692, section 6.1).
+            if frame.rsv1
+                && (!self.compressed || frame.opcode.is_control() || self.partial.is_some())
+            {
+                return Err(FrameError::Protocol(
+                    frame::PROTOCOL_ERROR,
+                    "unexpected reserved bits",
+                ));
+            }
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    self.sender.pong(frame.payload.clone()).await?;
+
+                    return Ok(Message::Ping(frame.payload));
+                }
+                OpCode::Pong => return Ok(Message::Pong(frame.payload)),
+                OpCode::Close => {
+                    let reason = CloseFrame::parse(&frame.payload)?;
+
+                    // Echo the code back to finish the closing handshake (RFC 6455, section 5.5.1).
+                    let code = reason.as_ref().map_or(frame::NORMAL, |reason| reason.code);
+                    self.sender.close(code, "").await?;
+
+                    return Ok(Message::Close(reason));
+                }
+                OpCode::Text | OpCode::Binary if self.partial.is_some() => {
+                    return Err(FrameError::Protocol(
+                        frame::PROTOCOL_ERROR,
+                        "expected a continuation frame",
+                    ));
+                }
+                OpCode::Text | OpCode::Binary => {
+                    self.partial = Some((frame.opcode, frame.rsv1, frame.payload));
+                }
+                OpCode::Continuation => {
+                    let Some((_, _, payload)) = self.partial.as_mut() else {
+                        return Err(FrameError::Protocol(
+                            frame::PROTOCOL_ERROR,
+                            "unexpected continuation frame",
+                        ));
+                    };
+
+                    payload.extend_from_slice(&frame.payload);
+                }
+            }
+
+            if self
+                .partial
+                .as_ref()
+                .is_some_and(|(_, _, payload)| payload.len() > self.config.max_message_size)
+            {
+                return Err(FrameError::Protocol(frame::TOO_BIG, "message too large"));
+            }
+
+            if frame.fin {
+                if let Some((opcode, compressed, payload)) = self.partial.take() {
+                    #[cfg(feature = "deflate")]
+                    let payload = match self.inflater.as_mut().filter(|_| compressed) {
+                        Some(inflater) => inflater
+                            .inflate(&payload, self.config.max_message_size)
+                            .map_err(|_| {
+                                FrameError::Protocol(frame::INVALID_DATA, "invalid compressed data")
+                            })?
+                            .ok_or(FrameError::Protocol(frame::TOO_BIG, "message too large"))?,
+                        None => payload,
+                    };
+                    #[cfg(not(feature = "deflate"))]
+                    debug_assert!(!compressed, "compression can't be negotiated");
+
+                    return data_message(opcode, payload);
+                }
+            }
+        }
+    }
+}
+
+/// Validate a complete data message.
+fn data_message(opcode: OpCode, payload: Vec<u8>) -> Result<Message, FrameError> {
+    if opcode == OpCode::Text {
+        return String::from_utf8(payload).map(Message::Text).map_err(|_| {
+            FrameError::Protocol(frame::INVALID_DATA, "text message is not valid UTF-8")
+        });
+    }
+
+    Ok(Message::Binary(payload))
+}
+
+/// Perform the opening handshake for `request`, and hand the connection to `handler` once it's been upgraded.
+pub fn accept<F, Fut>(request: Request, config: WebSocketConfig, handler: Arc<F>) -> Response
+where
+    F: Fn(Request, WebSocket) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    let mut response = handshake::response(&request);
+    if response.status_code != StatusCode::SwitchingProtocols {
+        return response;
+    }
+
+    #[cfg(feature = "deflate")]
+    let extension = request
+        .headers
+        .get("sec-websocket-extensions")
+        .filter(|_| config.compression)
+        .and_then(|extensions| deflate::negotiate(extensions));
+    #[cfg(feature = "deflate")]
+    if let Some(extension) = &extension {
+        response = response.header("Sec-WebSocket-Extensions", extension);
+    }
+    #[cfg(feature = "deflate")]
+    let compressed = extension.is_some();
+    #[cfg(not(feature = "deflate"))]
+    let compressed = false;
+
+    response.on_upgrade(move |io| async move {
+        let shutdown = io.shutting_down();
+        let socket = WebSocket::new(io, config, compressed);
+        let sender = socket.sender.clone();
+
+        let handler = handler(request, socket);
+        tokio::pin!(handler);
+
+        let result = tokio::select! {
+            result = &mut handler => result,
+            () = shutdown => {
+                // The handler finishes once the client answers, as with any other closing handshake.
+                _ = sender.close(frame::GOING_AWAY, "server shutting down").await;
+
+                handler.await
+            }
+        };
+
+        let code = match result {
+            Ok(()) => frame::NORMAL,
+            Err(error) => {
+                println!("error: websocket handler failed: {error}");
+
+                frame::INTERNAL_ERROR
+            }
+        };
+
+        _ = sender.finish(code).await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+    use frame::masked;
+    use std::{net::SocketAddr, time::Duration};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::{mpsc, oneshot},
+        time::timeout,
+    };
+
+    /// Start a server with an echo endpoint at `/echo`, limited to 64-byte frames and 128-byte messages,
+    /// and compressing messages for clients that ask for it.
+    async fn echo_server() -> SocketAddr {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+
+        let config = WebSocketConfig {
+            max_frame_size: 64,
+            max_message_size: 128,
+            #[cfg(feature = "deflate")]
+            compression: true,
+        };
+
+        http.routes.websocket_with_config(
+            "/echo",
+            config,
+            |_: Request, mut socket: WebSocket| async move {
+                while let Some(message) = socket.recv().await? {
+                    if let Message::Text(_) | Message::Binary(_) = message {
+                        socket.send(message).await?;
+                    }
+                }
+
+                Ok(())
+            },
+        );
+
+        tokio::spawn(async move { http.run().await });
+
+        addr
+    }
+
+    /// Open a connection to `/echo` with the given extra headers, and return it along with the handshake response.
+    async fn connect(addr: SocketAddr, headers: &str) -> (TcpStream, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET /echo HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                     Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n{headers}\r\n"
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+
+        (stream, String::from_utf8(head).unwrap())
+    }
+
+    /// Read an unmasked frame from the server, returning its first byte and payload.
+    async fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let first = stream.read_u8().await.unwrap();
+        let length = stream.read_u8().await.unwrap();
+        assert!(length < 126, "unexpected frame length");
+
+        let mut payload = vec![0; usize::from(length)];
+        stream.read_exact(&mut payload).await.unwrap();
+
+        (first, payload)
+    }
+
+    #[tokio::test]
+    async fn test_performs_handshake() {
+        let addr = echo_server().await;
+        let (_, head) = connect(addr, "").await;
+
+        assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(head.contains("Upgrade: websocket\r\n"));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /echo HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_echoes_messages() {
+        let (mut stream, _) = connect(echo_server().await, "").await;
+
+        stream.write_all(&masked(0x81, b"Hello")).await.unwrap();
+        assert_eq!(read_frame(&mut stream).await, (0x81, b"Hello".to_vec()));
+
+        // A fragmented binary message, with a ping in between.
+        stream.write_all(&masked(0x02, b"lil")).await.unwrap();
+        stream.write_all(&masked(0x89, b"ping")).await.unwrap();
+        stream.write_all(&masked(0x80, b"-http")).await.unwrap();
+        assert_eq!(read_frame(&mut stream).await, (0x8A, b"ping".to_vec()));
+        assert_eq!(read_frame(&mut stream).await, (0x82, b"lil-http".to_vec()));
+
+        stream
+            .write_all(&masked(0x88, &[0x03, 0xE8, b'b', b'y', b'e']))
+            .await
+            .unwrap();
+        assert_eq!(read_frame(&mut stream).await, (0x88, vec![0x03, 0xE8]));
+
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[cfg(feature = "deflate")]
+    #[tokio::test]
+    async fn test_compresses_messages() {
+        let (mut stream, head) = connect(
+            echo_server().await,
+            "Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n",
+        )
+        .await;
+        assert!(head.contains(
+            "Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover\r\n"
+        ));
+
+        let compressed = deflate::Deflater::new().deflate(b"Hello").unwrap();
+        stream.write_all(&masked(0xC1, &compressed)).await.unwrap();
+
+        let (first, payload) = read_frame(&mut stream).await;
+        assert_eq!(first, 0xC1);
+        assert_eq!(
+            deflate::Inflater::new().inflate(&payload, 1024).unwrap(),
+            Some(b"Hello".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_closes_connections_on_shutdown() {
+        let mut http = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = http.local_addr().unwrap();
+        let (received, mut messages) = mpsc::unbounded_channel();
+        http.routes
+            .websocket("/echo", move |_: Request, mut socket: WebSocket| {
+                let received = received.clone();
+
+                async move {
+                    while let Some(message) = socket.recv().await? {
+                        _ = received.send(message);
+                    }
+
+                    Ok(())
+                }
+            });
+
+        let (shutdown, signal) = oneshot::channel();
+        let server = tokio::spawn(async move {
+            http.run_with_shutdown(async {
+                _ = signal.await;
+            })
+            .await;
+        });
+
+        let (mut stream, _) = connect(addr, "").await;
+        shutdown.send(()).unwrap();
+
+        let (first, payload) = read_frame(&mut stream).await;
+        assert_eq!(first, 0x88);
+        assert_eq!(
+            u16::from_be_bytes([payload[0], payload[1]]),
+            frame::GOING_AWAY
+        );
+
+        // Pings arriving after the server started closing are no longer answered.
+        stream.write_all(&masked(0x89, b"ping")).await.unwrap();
+        stream
+            .write_all(&masked(0x88, &payload[..2]))
+            .await
+            .unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+
+        timeout(Duration::from_secs(5), server)
+            .await
+            .expect("the server didn't shut down")
+            .unwrap();
+
+        assert_eq!(messages.recv().await, Some(Message::Ping(b"ping".to_vec())));
+        assert_eq!(
+            messages.recv().await,
+            Some(Message::Close(Some(CloseFrame {
+                code: frame::GOING_AWAY,
+                reason: String::new(),
+            })))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_protocol_violations() {
+        let addr = echo_server().await;
+
+        let cases: [(Vec<u8>, u16); 4] = [
+            (vec![0x81, 0x02, b'h', b'i'], frame::PROTOCOL_ERROR),
+            (masked(0x81, &[0xC3, 0x28]), frame::INVALID_DATA),
+            (masked(0x82, &[0; 100]), frame::TOO_BIG),
+            (
+                [
+                    masked(0x02, &[0; 64]),
+                    masked(0x00, &[0; 64]),
+                    masked(0x80, &[0; 1]),
+                ]
+                .concat(),
+                frame::TOO_BIG,
+            ),
+        ];
+
+        for (bytes, code) in cases {
+            let (mut stream, _) = connect(addr, "").await;
+            stream.write_all(&bytes).await.unwrap();
+
+            let (first, payload) = read_frame(&mut stream).await;
+            assert_eq!(first, 0x88);
+            assert_eq!(u16::from_be_bytes([payload[0], payload[1]]), code);
+        }
+    }
+}
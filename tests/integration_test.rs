@@ -1,51 +1,57 @@
-use lil_http::{Body, Response, Server};
+use lil_http::{Body, BodyStream, Request, Response, Server};
 use serde_json::{json, Value};
-use serial_test::serial;
 use std::collections::HashMap;
 
 #[tokio::test]
-#[serial]
 async fn test_http_lifecycle() {
-    let mut server = Server::new().await.unwrap();
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
 
-    server.routes.get("/", |_| Response::text("Hello, World!"));
+    server
+        .routes
+        .get("/", |_: Request| Response::text("Hello, World!"));
 
     tokio::spawn(async move {
         server.run().await;
     });
 
     let client = reqwest::Client::new();
-    let response = client.get("http://localhost:3000/").send().await.unwrap();
+    let response = client.get(format!("http://{addr}/")).send().await.unwrap();
 
     assert_eq!(response.status(), 200);
     assert_eq!(response.text().await.unwrap(), "Hello, World!");
 }
 
 #[tokio::test]
-#[serial]
 async fn test_http_lifecycle_with_port() {
-    let mut server = Server::with_port("3001").await.unwrap();
+    let mut server = Server::with_port("0").await.unwrap();
+    let port = server.local_addr().unwrap().port();
 
-    server.routes.get("/", |_| Response::text("Hello, World!"));
+    server
+        .routes
+        .get("/", |_: Request| Response::text("Hello, World!"));
 
     tokio::spawn(async move {
         server.run().await;
     });
 
     let client = reqwest::Client::new();
-    let response = client.get("http://localhost:3001/").send().await.unwrap();
+    let response = client
+        .get(format!("http://127.0.0.1:{port}/"))
+        .send()
+        .await
+        .unwrap();
 
     assert_eq!(response.status(), 200);
     assert_eq!(response.text().await.unwrap(), "Hello, World!");
 }
 
 #[tokio::test]
-#[serial]
 async fn test_post_with_body_and_response() {
-    let mut server = Server::new().await.unwrap();
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
 
-    server.routes.post("/", |request| {
-        dbg!(&request.body);
+    server.routes.post("/", |request: Request| {
         let Body::Json(body) = request.body else {
             return Response::invalid_request();
         };
@@ -68,7 +74,7 @@ async fn test_post_with_body_and_response() {
 
     let client = reqwest::Client::new();
     let response = client
-        .post("http://localhost:3000/")
+        .post(format!("http://{addr}/"))
         .json(&body)
         .send()
         .await
@@ -80,3 +86,126 @@ async fn test_post_with_body_and_response() {
         json!({ "message": "Hello, Miguel!" })
     );
 }
+
+#[tokio::test]
+async fn test_panicking_handler_returns_500() {
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    server
+        .routes
+        .get("/", || -> Response { panic!("handler panicked") })
+        .get("/healthy", || "Still here!");
+
+    tokio::spawn(async move {
+        server.run().await;
+    });
+
+    let client = reqwest::Client::new();
+    let response = client.get(format!("http://{addr}/")).send().await.unwrap();
+
+    assert_eq!(response.status(), 500);
+    assert_eq!(response.text().await.unwrap(), "Internal Server Error");
+
+    let response = client
+        .get(format!("http://{addr}/healthy"))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "Still here!");
+}
+
+#[tokio::test]
+async fn test_post_with_large_body() {
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    server.routes.post("/", |request: Request| {
+        let Body::Text(body) = request.body else {
+            return Response::invalid_request();
+        };
+
+        Response::text(&body.len().to_string())
+    });
+
+    tokio::spawn(async move {
+        server.run().await;
+    });
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{addr}/"))
+        .body("a".repeat(100_000))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "100000");
+}
+
+#[tokio::test]
+async fn test_streaming_response() {
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    server.routes.get("/", || {
+        let (sender, body) = BodyStream::channel(1);
+
+        tokio::spawn(async move {
+            for i in 0..1000 {
+                sender.send(format!("{i}\n").into_bytes()).await.unwrap();
+            }
+        });
+
+        Response::stream(body).header("Content-Type", "text/plain")
+    });
+
+    tokio::spawn(async move {
+        server.run().await;
+    });
+
+    let client = reqwest::Client::new();
+    let response = client.get(format!("http://{addr}/")).send().await.unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.text().await.unwrap(),
+        (0..1000).map(|i| format!("{i}\n")).collect::<String>()
+    );
+}
+
+#[tokio::test]
+async fn test_binary_body_roundtrip() {
+    let mut server = Server::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    server.routes.post("/", |body: Body| {
+        let Body::Bytes(bytes) = body else {
+            return Response::invalid_request();
+        };
+
+        Response::bytes(&bytes.into_iter().rev().collect::<Vec<u8>>())
+    });
+
+    tokio::spawn(async move {
+        server.run().await;
+    });
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("http://{addr}/"))
+        .header("Content-Type", "application/octet-stream")
+        .body(vec![0, 159, 146, 150, 255])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.bytes().await.unwrap().to_vec(),
+        vec![255, 150, 146, 159, 0]
+    );
+}